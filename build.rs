@@ -0,0 +1,99 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_SCHEMAS").is_some() {
+        libxml2();
+    }
+    if env::var_os("CARGO_FEATURE_EMBED_SCHEMAS").is_some() {
+        embutir_schemas();
+    }
+    if env::var_os("CARGO_FEATURE_EMBED_ICP_BRASIL").is_some() {
+        embutir_icp_brasil();
+    }
+}
+
+/// Localiza a libxml2 via `pkg-config`, que também resolve as dependências da biblioteca estática
+/// quando `PKG_CONFIG_ALL_STATIC` ou `LIBXML_2_0_STATIC` estão definidas, e.g. em builds musl.
+fn libxml2() {
+    if let Err(err) = pkg_config::Config::new().probe("libxml-2.0") {
+        println!("cargo:warning=libxml-2.0 não encontrada via pkg-config, usando -lxml2: {err}");
+        println!("cargo:rustc-link-lib=xml2");
+    }
+}
+
+/// Gera a lista de schemas XSD de `resources/schemas` embutidos na biblioteca.
+fn embutir_schemas() {
+    let mut codigo = String::from("&[\n");
+    for caminho in listar(
+        "schemas",
+        &["xsd"],
+        "embed_schemas",
+        "build_schemas.sh PL_009_V4.zip",
+    ) {
+        let nome = caminho.file_name().unwrap().to_string_lossy();
+        codigo.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            nome,
+            caminho.display().to_string()
+        ));
+    }
+    codigo.push(']');
+    gravar("schemas.rs", codigo);
+}
+
+/// Gera a lista de certificados da cadeia ICP-Brasil de `resources/icp_brasil` embutidos na
+/// biblioteca.
+fn embutir_icp_brasil() {
+    let mut codigo = String::from("&[\n");
+    for caminho in listar(
+        "icp_brasil",
+        &["crt", "cer", "pem"],
+        "embed_icp_brasil",
+        "build_icp_brasil.sh ACcompactado.zip",
+    ) {
+        codigo.push_str(&format!(
+            "    include_bytes!({:?}),\n",
+            caminho.display().to_string()
+        ));
+    }
+    codigo.push(']');
+    gravar("icp_brasil.rs", codigo);
+}
+
+/// Lista, em ordem, os arquivos de `resources/<nome>` com as extensões informadas, interrompendo o
+/// build caso não haja nenhum, pois a _feature_ habilitada embutiria uma lista vazia e falharia
+/// apenas em tempo de execução.
+fn listar(nome: &str, extensoes: &[&str], feature: &str, script: &str) -> Vec<PathBuf> {
+    let diretorio = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap())
+        .join("resources")
+        .join(nome);
+    println!("cargo:rerun-if-changed={}", diretorio.display());
+    let mut arquivos: Vec<_> = fs::read_dir(&diretorio)
+        .into_iter()
+        .flatten()
+        .filter_map(|entrada| entrada.ok().map(|entrada| entrada.path()))
+        .filter(|caminho| {
+            caminho
+                .extension()
+                .is_some_and(|extensao| extensoes.iter().any(|e| extensao.eq_ignore_ascii_case(e)))
+        })
+        .collect();
+    arquivos.sort();
+    assert!(
+        !arquivos.is_empty(),
+        "feature {} habilitada sem arquivos em {}, execute resources/{}",
+        feature,
+        diretorio.display(),
+        script
+    );
+    arquivos
+}
+
+fn gravar(nome: &str, codigo: String) {
+    let destino = Path::new(&env::var_os("OUT_DIR").unwrap()).join(nome);
+    fs::write(destino, codigo).unwrap();
+}
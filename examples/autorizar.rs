@@ -0,0 +1,34 @@
+//! Exemplo básico de como transmitir nota assinada para autorização síncrona.
+
+use std::env;
+
+use fiscalidade::{Ambiente, Dfe, Modelo, Pkcs12Certificate, Processamento, Uf, WebServices};
+use tokio::fs;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 8 {
+        println!("Uso: <certificado.pfx> <senha> <modelo> <uf> <ambiente> <lote> <nfe.xml>");
+        return Ok(());
+    }
+    #[cfg(feature = "embed_webservices")]
+    let webservices = WebServices::from_embedded()?;
+    #[cfg(not(feature = "embed_webservices"))]
+    let webservices = WebServices::from_file("resources/webservices.toml").await?;
+    let pkcs12 = Pkcs12Certificate::from_file(&args[1], &args[2]).await?;
+    let nfe = fs::read_to_string(&args[7]).await?;
+    let dfe = Dfe::new().set_webservices(webservices).set_pkcs12(pkcs12);
+    let xml = dfe
+        .autorizar(
+            Modelo::from_str(&args[3]).unwrap(),
+            Uf::from_str(&args[4]).unwrap(),
+            Ambiente::from_str(&args[5]).unwrap(),
+            &args[6],
+            Processamento::Sincrono,
+            &[&nfe],
+        )
+        .await?;
+    println!("XML retornado: {}", xml);
+    Ok(())
+}
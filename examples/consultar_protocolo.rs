@@ -2,7 +2,7 @@
 
 use std::env;
 
-use fiscalidade::{Ambiente, Dfe, Modelo, Pkcs12Certificate, Uf, WebServices};
+use fiscalidade::{Ambiente, Dfe, Modelo, Pkcs12Certificate, RetConsSitNFe, Uf, WebServices};
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
@@ -26,5 +26,7 @@ async fn main() -> anyhow::Result<()> {
         )
         .await?;
     println!("XML retornado: {}", xml);
+    let retorno = xml.retorno::<RetConsSitNFe>()?;
+    println!("Situação da nota: {:?}", retorno.situacao());
     Ok(())
 }
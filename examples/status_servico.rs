@@ -2,7 +2,7 @@
 
 use std::env;
 
-use fiscalidade::{Ambiente, Dfe, Modelo, Pkcs12Certificate, Uf, WebServices};
+use fiscalidade::{Ambiente, Dfe, Modelo, Pkcs12Certificate, RetConsStatServ, Uf, WebServices};
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
@@ -25,5 +25,12 @@ async fn main() -> anyhow::Result<()> {
         )
         .await?;
     println!("XML retornado: {}", xml);
+    let status = xml.retorno::<RetConsStatServ>()?;
+    println!(
+        "Serviço em operação: {} ({} - {})",
+        status.em_operacao(),
+        status.c_stat,
+        status.x_motivo
+    );
     Ok(())
 }
@@ -0,0 +1,34 @@
+#!/bin/sh
+
+# Gera a AC, o certificado do servidor (localhost) e os certificados A1 da matriz e da filial do
+# cliente usados pelo mock.
+
+set -e
+
+DIAS=36500
+SENHA=mock
+
+openssl req -x509 -newkey rsa:2048 -nodes -sha256 -days $DIAS \
+    -subj "/CN=Fiscalidade Mock AC" \
+    -addext "basicConstraints=critical,CA:TRUE" \
+    -addext "keyUsage=critical,keyCertSign,cRLSign" \
+    -keyout ac.key -out ac.pem
+
+openssl req -newkey rsa:2048 -nodes -sha256 -subj "/CN=localhost" -keyout servidor.key -out servidor.csr
+printf "subjectAltName=DNS:localhost,IP:127.0.0.1\nextendedKeyUsage=serverAuth\n" >servidor.ext
+openssl x509 -req -sha256 -days $DIAS -in servidor.csr -CA ac.pem -CAkey ac.key -CAcreateserial \
+    -extfile servidor.ext -out servidor.pem
+
+printf "extendedKeyUsage=clientAuth\n" >cliente.ext
+cliente() {
+    openssl req -newkey rsa:2048 -nodes -sha256 -subj "/CN=$2" -keyout "$1.key" -out "$1.csr"
+    openssl x509 -req -sha256 -days $DIAS -in "$1.csr" -CA ac.pem -CAkey ac.key -CAcreateserial \
+        -extfile cliente.ext -out "$1.pem"
+    openssl pkcs12 -export -inkey "$1.key" -in "$1.pem" -certfile ac.pem \
+        -passout pass:$SENHA -out "$1.pfx"
+    rm -f "$1.key" "$1.csr" "$1.pem"
+}
+cliente cliente "EMPRESA MOCK:11222333000181"
+cliente cliente_filial "EMPRESA MOCK FILIAL:11222333000262"
+
+rm -f ac.key ac.srl servidor.csr servidor.ext cliente.ext
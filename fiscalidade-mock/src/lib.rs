@@ -0,0 +1,609 @@
+//! `fiscalidade-mock` Servidor HTTPS local que simula os webservices SOAP da SEFAZ.
+//!
+//! Permite testar o fluxo completo do `fiscalidade` sem certificado real e sem acesso à rede: o
+//! servidor exige autenticação do client com o certificado A1 [`PKCS12`], emitido pela AC
+//! [`CERTIFICADO_AC`], que também emite o certificado do servidor para `localhost`. O TOML
+//! retornado por [`MockSefaz::webservices`] aponta todas as UFs para o servidor.
+
+mod respostas;
+
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    future, io,
+    sync::{Arc, Mutex},
+};
+
+use roxmltree::{Document, Node};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    task::JoinHandle,
+};
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        server::WebPkiClientVerifier,
+        RootCertStore, ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+/// Certificado da AC do mock, em PEM, a ser adicionado como certificado raiz do client.
+pub const CERTIFICADO_AC: &[u8] = include_bytes!("../resources/ac.pem");
+
+/// Certificado A1 do client, em PKCS #12, aceito pelo mock.
+pub const PKCS12: &[u8] = include_bytes!("../resources/cliente.pfx");
+
+/// Senha do certificado [`PKCS12`].
+pub const SENHA_PKCS12: &str = "mock";
+
+/// CNPJ do titular do certificado [`PKCS12`].
+pub const CNPJ: &str = "11222333000181";
+
+/// Certificado A1 de uma filial do client, em PKCS #12, também aceito pelo mock e com a mesma senha
+/// de [`PKCS12`].
+pub const PKCS12_FILIAL: &[u8] = include_bytes!("../resources/cliente_filial.pfx");
+
+/// CNPJ do titular do certificado [`PKCS12_FILIAL`].
+pub const CNPJ_FILIAL: &str = "11222333000262";
+
+/// Chave da NF-e retornada pela distribuição de DF-e.
+pub const CHAVE: &str = "51240111222333000181550010000000011000000010";
+
+/// Último NSU disponível na distribuição de DF-e.
+const MAX_NSU: u64 = 1;
+
+const CERTIFICADO_SERVIDOR: &[u8] = include_bytes!("../resources/servidor.pem");
+const CHAVE_SERVIDOR: &[u8] = include_bytes!("../resources/servidor.key");
+
+/// Seções de UF, autorizadores e ambiente nacional do TOML de webservices.
+const SECOES: &[&str] = &[
+    "AC", "AL", "AP", "AM", "BA", "CE", "DF", "ES", "GO", "MA", "MG", "MS", "MT", "PA", "PB", "PE",
+    "PI", "PR", "RJ", "RN", "RO", "RS", "RR", "SC", "SE", "SP", "TO", "SVAN", "SVRS", "SVC-AN",
+    "SVC-RS", "AN",
+];
+
+/// Chaves de serviço do TOML de webservices.
+const SERVICOS: &[&str] = &[
+    "NfeStatusServico_4.00",
+    "NfeConsultaProtocolo_4.00",
+    "NFeRetAutorizacao_4.00",
+    "NfeConsultaCadastro_4.00",
+    "NfeAutorizacao_4.00",
+    "RecepcaoEvento_4.00",
+    "NfeInutilizacao_4.00",
+    "NFeDistribuicaoDFe_1.01",
+    "URL-QRCode",
+    "URL-ConsultaNFCe",
+];
+
+/// Operações simuladas, identificadas pela tag raiz da mensagem enviada.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Operacao {
+    ConsStatServ,
+    ConsSitNfe,
+    ConsReciNfe,
+    ConsCad,
+    EnviNfe,
+    EnvEvento,
+    InutNfe,
+    DistDfeInt,
+}
+
+impl Operacao {
+    const TODAS: [Operacao; 8] = [
+        Operacao::ConsStatServ,
+        Operacao::ConsSitNfe,
+        Operacao::ConsReciNfe,
+        Operacao::ConsCad,
+        Operacao::EnviNfe,
+        Operacao::EnvEvento,
+        Operacao::InutNfe,
+        Operacao::DistDfeInt,
+    ];
+
+    /// Tag raiz da mensagem da operação, e.g. `consStatServ`.
+    pub fn tag(&self) -> &str {
+        use self::Operacao::*;
+        match *self {
+            ConsStatServ => "consStatServ",
+            ConsSitNfe => "consSitNFe",
+            ConsReciNfe => "consReciNFe",
+            ConsCad => "ConsCad",
+            EnviNfe => "enviNFe",
+            EnvEvento => "envEvento",
+            InutNfe => "inutNFe",
+            DistDfeInt => "distDFeInt",
+        }
+    }
+
+    #[inline]
+    fn from_tag(tag: &str) -> Option<Self> {
+        Self::TODAS
+            .into_iter()
+            .find(|operacao| operacao.tag() == tag)
+    }
+}
+
+/// Cenário de resposta do mock.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Cenario {
+    /// Processa com sucesso: serviço em operação, NF-e autorizada, evento registrado etc.
+    Autorizado,
+    /// Rejeita com o `cStat` e `xMotivo` informados no nível em que a SEFAZ os retorna: no
+    /// `protNFe` para NF-e em lote processado e no envio síncrono, no `retEvento` para eventos e
+    /// no retorno da operação nos demais casos.
+    Rejeitado { c_stat: u16, x_motivo: String },
+    /// Responde lote em processamento (105) nas consultas de recibo e lote recebido (103) no envio
+    /// síncrono; nas demais operações equivale a [`Cenario::Autorizado`].
+    EmProcessamento,
+    /// Recebe a requisição mas nunca responde.
+    Timeout,
+    /// Responde HTTP 500 com SOAP Fault.
+    SoapFault,
+    /// Responde HTTP 503 uma única vez e depois equivale a [`Cenario::Autorizado`].
+    Indisponivel,
+}
+
+/// Requisição recebida pelo mock.
+#[derive(Clone, Debug)]
+pub struct Requisicao {
+    /// Operação identificada na mensagem, se conhecida.
+    pub operacao: Option<Operacao>,
+    /// Cabeçalho `SOAPAction` recebido.
+    pub action: String,
+    /// Envelope SOAP recebido.
+    pub corpo: String,
+}
+
+struct Estado {
+    cenario: Cenario,
+    cenarios: HashMap<Operacao, Cenario>,
+    requisicoes: Vec<Requisicao>,
+    lotes: HashMap<String, Vec<String>>,
+    sequencia: u64,
+}
+
+/// Servidor HTTPS local simulando os webservices da SEFAZ, encerrado ao sair de escopo.
+pub struct MockSefaz {
+    url: String,
+    estado: Arc<Mutex<Estado>>,
+    servidor: JoinHandle<()>,
+}
+
+impl MockSefaz {
+    /// Inicia o servidor em porta livre de `localhost`, respondendo com [`Cenario::Autorizado`].
+    pub async fn iniciar() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("https://localhost:{}", listener.local_addr()?.port());
+        let acceptor = TlsAcceptor::from(Arc::new(configurar_tls()?));
+        let estado = Arc::new(Mutex::new(Estado {
+            cenario: Cenario::Autorizado,
+            cenarios: HashMap::new(),
+            requisicoes: Vec::new(),
+            lotes: HashMap::new(),
+            sequencia: 0,
+        }));
+        let servidor = tokio::spawn(aceitar(listener, acceptor, estado.clone()));
+        Ok(Self {
+            url,
+            estado,
+            servidor,
+        })
+    }
+
+    /// URL base do servidor, e.g. `https://localhost:8443`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// TOML de webservices com todos os serviços, UFs e ambientes apontando para o servidor.
+    pub fn webservices(&self) -> String {
+        let mut toml = String::new();
+        for modelo in ["NFe", "NFCe"] {
+            for secao in SECOES {
+                for ambiente in ["P", "H"] {
+                    toml.push_str(&format!("[{}_{}_{}]\n", modelo, secao, ambiente));
+                    for servico in SERVICOS {
+                        toml.push_str(&format!("\"{0}\" = \"{1}/{0}\"\n", servico, self.url));
+                    }
+                    toml.push('\n');
+                }
+            }
+        }
+        toml
+    }
+
+    /// Aplica cenário a todas as operações sem cenário próprio.
+    pub fn set_cenario(&self, cenario: Cenario) {
+        self.estado.lock().unwrap().cenario = cenario;
+    }
+
+    /// Aplica cenário a uma única operação, com prioridade sobre [`MockSefaz::set_cenario`].
+    pub fn set_cenario_operacao(&self, operacao: Operacao, cenario: Cenario) {
+        self.estado
+            .lock()
+            .unwrap()
+            .cenarios
+            .insert(operacao, cenario);
+    }
+
+    /// Requisições recebidas até o momento, em ordem de chegada.
+    pub fn requisicoes(&self) -> Vec<Requisicao> {
+        self.estado.lock().unwrap().requisicoes.clone()
+    }
+}
+
+impl Drop for MockSefaz {
+    fn drop(&mut self) {
+        self.servidor.abort();
+    }
+}
+
+impl Estado {
+    /// Resposta HTTP à requisição, ou `None` para não responder.
+    fn responder(&mut self, action: String, corpo: String) -> Option<(u16, String)> {
+        let doc = Document::parse(&corpo).ok();
+        let mensagem = doc.as_ref().and_then(|doc| {
+            doc.descendants().find_map(|node| {
+                Operacao::from_tag(node.tag_name().name()).map(|operacao| (operacao, node))
+            })
+        });
+        self.requisicoes.push(Requisicao {
+            operacao: mensagem.map(|(operacao, _)| operacao),
+            action,
+            corpo: corpo.clone(),
+        });
+        let Some((operacao, node)) = mensagem else {
+            return Some((
+                500,
+                respostas::soap_fault("soap:Sender", "Mensagem não reconhecida"),
+            ));
+        };
+        let cenario = self
+            .cenarios
+            .get(&operacao)
+            .unwrap_or(&self.cenario)
+            .clone();
+        match cenario {
+            Cenario::Timeout => None,
+            Cenario::Indisponivel => {
+                match self.cenarios.get_mut(&operacao) {
+                    Some(cenario) => *cenario = Cenario::Autorizado,
+                    None => self.cenario = Cenario::Autorizado,
+                }
+                Some((503, String::new()))
+            }
+            Cenario::SoapFault => Some((
+                500,
+                respostas::soap_fault("soap:Receiver", "Erro interno no servidor"),
+            )),
+            cenario => Some((
+                200,
+                respostas::envelope(operacao, &self.retorno(operacao, node, &cenario)),
+            )),
+        }
+    }
+
+    fn retorno(&mut self, operacao: Operacao, node: Node, cenario: &Cenario) -> String {
+        let tp_amb = texto(node, "tpAmb");
+        let rejeitado = matches!(cenario, Cenario::Rejeitado { .. });
+        match operacao {
+            Operacao::ConsStatServ => {
+                let (c_stat, x_motivo) = situacao(cenario, 107, "Serviço em Operação");
+                respostas::ret_cons_stat_serv(&tp_amb, c_stat, &x_motivo, &texto(node, "cUF"))
+            }
+            Operacao::ConsSitNfe => {
+                let chave = texto(node, "chNFe");
+                let (c_stat, x_motivo) = situacao(cenario, 100, "Autorizado o uso da NF-e");
+                let prot = if rejeitado {
+                    String::new()
+                } else {
+                    self.prot_nfes(&tp_amb, std::slice::from_ref(&chave), cenario)
+                };
+                respostas::ret_cons_sit_nfe(&tp_amb, c_stat, &x_motivo, c_uf(&chave), &chave, &prot)
+            }
+            Operacao::ConsReciNfe => {
+                let n_rec = texto(node, "nRec");
+                if *cenario == Cenario::EmProcessamento {
+                    return respostas::ret_cons_reci_nfe(
+                        &tp_amb,
+                        &n_rec,
+                        105,
+                        "Lote em processamento",
+                        c_uf(&n_rec),
+                        "",
+                    );
+                }
+                let chaves = self.lotes.get(&n_rec).cloned().unwrap_or_default();
+                let prot = self.prot_nfes(&tp_amb, &chaves, cenario);
+                respostas::ret_cons_reci_nfe(
+                    &tp_amb,
+                    &n_rec,
+                    104,
+                    "Lote processado",
+                    c_uf(&n_rec),
+                    &prot,
+                )
+            }
+            Operacao::ConsCad => {
+                let (c_stat, x_motivo) =
+                    situacao(cenario, 111, "Consulta cadastro com uma ocorrência");
+                let uf = texto(node, "UF");
+                let documento = ecoar(node, &["IE", "CNPJ", "CPF"]);
+                let inf_cad = if rejeitado {
+                    String::new()
+                } else {
+                    respostas::inf_cad(&uf, &ecoar(node, &["CNPJ", "CPF"]))
+                };
+                respostas::ret_cons_cad(c_stat, &x_motivo, &uf, &documento, &inf_cad)
+            }
+            Operacao::EnviNfe => {
+                let c_uf = texto(node, "cUF");
+                let chaves: Vec<String> = node
+                    .descendants()
+                    .filter(|n| n.tag_name().name() == "infNFe")
+                    .filter_map(|n| n.attribute("Id"))
+                    .map(|id| id.trim_start_matches("NFe").to_string())
+                    .collect();
+                if texto(node, "indSinc") == "1" && *cenario != Cenario::EmProcessamento {
+                    let prot = self.prot_nfes(&tp_amb, &chaves, cenario);
+                    return respostas::ret_envi_nfe(&tp_amb, 104, "Lote processado", &c_uf, &prot);
+                }
+                if rejeitado {
+                    let (c_stat, x_motivo) = situacao(cenario, 103, "");
+                    return respostas::ret_envi_nfe(&tp_amb, c_stat, &x_motivo, &c_uf, "");
+                }
+                self.sequencia += 1;
+                let n_rec = format!("{:0>2}{:013}", c_uf, self.sequencia);
+                let inf_rec = respostas::inf_rec(&n_rec);
+                self.lotes.insert(n_rec, chaves);
+                respostas::ret_envi_nfe(&tp_amb, 103, "Lote recebido com sucesso", &c_uf, &inf_rec)
+            }
+            Operacao::EnvEvento => {
+                let c_orgao = texto(node, "cOrgao");
+                let mut ret_eventos = String::new();
+                for evento in node
+                    .descendants()
+                    .filter(|n| n.tag_name().name() == "infEvento")
+                {
+                    // 110140 - EPEC, registrado antes da autorização da nota
+                    let (c_stat, x_motivo) = if texto(evento, "tpEvento") == "110140" {
+                        situacao(cenario, 136, "Evento registrado, mas não vinculado a NF-e")
+                    } else {
+                        situacao(cenario, 135, "Evento registrado e vinculado a NF-e")
+                    };
+                    let n_prot = (!rejeitado).then(|| self.protocolo(&texto(evento, "cOrgao")));
+                    ret_eventos.push_str(&respostas::ret_evento(
+                        &tp_amb,
+                        &texto(evento, "cOrgao"),
+                        c_stat,
+                        &x_motivo,
+                        &ecoar(evento, &["chNFe", "tpEvento", "nSeqEvento"]),
+                        n_prot.as_deref(),
+                    ));
+                }
+                respostas::ret_env_evento(&texto(node, "idLote"), &tp_amb, &c_orgao, &ret_eventos)
+            }
+            Operacao::InutNfe => {
+                let (c_stat, x_motivo) =
+                    situacao(cenario, 102, "Inutilização de número homologado");
+                let c_uf = texto(node, "cUF");
+                let n_prot = (!rejeitado).then(|| self.protocolo(&c_uf));
+                respostas::ret_inut_nfe(
+                    &tp_amb,
+                    c_stat,
+                    &x_motivo,
+                    &ecoar(
+                        node,
+                        &["cUF", "ano", "CNPJ", "mod", "serie", "nNFIni", "nNFFin"],
+                    ),
+                    n_prot.as_deref(),
+                )
+            }
+            Operacao::DistDfeInt => {
+                if rejeitado {
+                    let (c_stat, x_motivo) = situacao(cenario, 137, "");
+                    return respostas::ret_dist_dfe_int(&tp_amb, c_stat, &x_motivo, 0, 0, "");
+                }
+                let nsu = if let Ok(ult_nsu) = texto(node, "ultNSU").parse::<u64>() {
+                    ult_nsu + 1
+                } else {
+                    texto(node, "NSU").parse().unwrap_or(1)
+                };
+                if nsu > MAX_NSU {
+                    return respostas::ret_dist_dfe_int(
+                        &tp_amb,
+                        137,
+                        "Nenhum documento localizado",
+                        MAX_NSU,
+                        MAX_NSU,
+                        "",
+                    );
+                }
+                let chave = Some(texto(node, "chNFe"))
+                    .filter(|chave| !chave.is_empty())
+                    .unwrap_or_else(|| CHAVE.to_string());
+                let n_prot = self.protocolo(c_uf(&chave));
+                respostas::ret_dist_dfe_int(
+                    &tp_amb,
+                    138,
+                    "Documento localizado",
+                    nsu,
+                    MAX_NSU,
+                    &respostas::doc_zip_res_nfe(nsu, &chave, &n_prot),
+                )
+            }
+        }
+    }
+
+    /// Protocolos das NF-e informadas conforme o cenário.
+    fn prot_nfes(&mut self, tp_amb: &str, chaves: &[String], cenario: &Cenario) -> String {
+        let (c_stat, x_motivo) = situacao(cenario, 100, "Autorizado o uso da NF-e");
+        let mut prot = String::new();
+        for chave in chaves {
+            let n_prot = (c_stat == 100).then(|| self.protocolo(c_uf(chave)));
+            prot.push_str(&respostas::prot_nfe(
+                tp_amb,
+                chave,
+                c_stat,
+                &x_motivo,
+                n_prot.as_deref(),
+            ));
+        }
+        prot
+    }
+
+    fn protocolo(&mut self, c_uf: &str) -> String {
+        self.sequencia += 1;
+        format!("1{:0>2}24{:010}", c_uf, self.sequencia)
+    }
+}
+
+/// `cStat` e `xMotivo` do cenário, usando os informados para sucesso.
+#[inline]
+fn situacao(cenario: &Cenario, c_stat: u16, x_motivo: &str) -> (u16, String) {
+    match cenario {
+        Cenario::Rejeitado { c_stat, x_motivo } => (*c_stat, x_motivo.clone()),
+        _ => (c_stat, x_motivo.to_string()),
+    }
+}
+
+/// Código da UF no início de chaves e recibos.
+#[inline]
+fn c_uf(chave: &str) -> &str {
+    chave.get(..2).unwrap_or_default()
+}
+
+/// Repete as tags informadas presentes na mensagem, e.g. para devolver o CNPJ consultado.
+fn ecoar(node: Node, tags: &[&str]) -> String {
+    tags.iter()
+        .filter_map(|tag| {
+            node.descendants()
+                .find(|n| n.tag_name().name() == *tag)
+                .map(|n| format!("<{0}>{1}</{0}>", tag, n.text().unwrap_or_default().trim()))
+        })
+        .collect()
+}
+
+#[inline]
+fn texto(node: Node, tag: &str) -> String {
+    node.descendants()
+        .find(|n| n.tag_name().name() == tag)
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn configurar_tls() -> io::Result<ServerConfig> {
+    let provider = Arc::new(ring::default_provider());
+    let mut raizes = RootCertStore::empty();
+    for certificado in CertificateDer::pem_slice_iter(CERTIFICADO_AC) {
+        raizes
+            .add(certificado.map_err(erro_tls)?)
+            .map_err(erro_tls)?;
+    }
+    // Assim como a SEFAZ, exige certificado do client.
+    let verificador = WebPkiClientVerifier::builder_with_provider(raizes.into(), provider.clone())
+        .build()
+        .map_err(erro_tls)?;
+    let cadeia = CertificateDer::pem_slice_iter(CERTIFICADO_SERVIDOR)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(erro_tls)?;
+    let chave = PrivateKeyDer::from_pem_slice(CHAVE_SERVIDOR).map_err(erro_tls)?;
+    ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(erro_tls)?
+        .with_client_cert_verifier(verificador)
+        .with_single_cert(cadeia, chave)
+        .map_err(erro_tls)
+}
+
+#[inline]
+fn erro_tls<E: Into<Box<dyn StdError + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+async fn aceitar(listener: TcpListener, acceptor: TlsAcceptor, estado: Arc<Mutex<Estado>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let acceptor = acceptor.clone();
+        let estado = estado.clone();
+        tokio::spawn(async move {
+            // Falhas de handshake e conexões interrompidas afetam somente o client que as causou.
+            if let Ok(stream) = acceptor.accept(stream).await {
+                let _ = atender(stream, &estado).await;
+            }
+        });
+    }
+}
+
+async fn atender<S>(mut stream: S, estado: &Mutex<Estado>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (action, corpo) = ler_requisicao(&mut stream).await?;
+    let resposta = estado.lock().unwrap().responder(action, corpo);
+    let Some((status, corpo)) = resposta else {
+        return future::pending().await;
+    };
+    let motivo = match status {
+        200 => "OK",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let cabecalho = format!(
+        concat!(
+            "HTTP/1.1 {status} {motivo}\r\n",
+            "Content-Type: application/soap+xml; charset=utf-8\r\n",
+            "Content-Length: {tamanho}\r\n",
+            "Connection: close\r\n\r\n"
+        ),
+        status = status,
+        motivo = motivo,
+        tamanho = corpo.len(),
+    );
+    stream.write_all(cabecalho.as_bytes()).await?;
+    stream.write_all(corpo.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Lê requisição HTTP/1.1 com `Content-Length`, retornando o `SOAPAction` e o corpo.
+async fn ler_requisicao<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<(String, String)> {
+    let mut dados = Vec::new();
+    let mut buffer = [0; 8192];
+    let fim_cabecalho = loop {
+        if let Some(posicao) = dados.windows(4).position(|janela| janela == b"\r\n\r\n") {
+            break posicao + 4;
+        }
+        let lidos = stream.read(&mut buffer).await?;
+        if lidos == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        dados.extend_from_slice(&buffer[..lidos]);
+    };
+    let cabecalho = String::from_utf8_lossy(&dados[..fim_cabecalho]).into_owned();
+    let valor = |nome: &str| {
+        cabecalho.lines().find_map(|linha| {
+            let (chave, valor) = linha.split_once(':')?;
+            chave
+                .trim()
+                .eq_ignore_ascii_case(nome)
+                .then(|| valor.trim().to_string())
+        })
+    };
+    let tamanho = valor("Content-Length")
+        .and_then(|tamanho| tamanho.parse().ok())
+        .unwrap_or(0);
+    while dados.len() < fim_cabecalho + tamanho {
+        let lidos = stream.read(&mut buffer).await?;
+        if lidos == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        dados.extend_from_slice(&buffer[..lidos]);
+    }
+    let corpo = String::from_utf8_lossy(&dados[fim_cabecalho..fim_cabecalho + tamanho]);
+    Ok((valor("SOAPAction").unwrap_or_default(), corpo.into_owned()))
+}
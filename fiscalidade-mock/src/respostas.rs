@@ -0,0 +1,460 @@
+use std::io::Write;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::{write::GzEncoder, Compression};
+
+use crate::{Operacao, CNPJ};
+
+const PORTAL_FISCAL: &str = "http://www.portalfiscal.inf.br/nfe";
+
+const VER_APLIC: &str = "MOCK-1.0";
+
+/// Data e hora fixas de todos os retornos, para facilitar comparações nos testes.
+const DH_RECBTO: &str = "2024-01-01T12:00:00-03:00";
+
+#[inline]
+fn opcional(tag: &str, valor: Option<&str>) -> String {
+    valor
+        .map(|valor| format!("<{0}>{1}</{0}>", tag, valor))
+        .unwrap_or_default()
+}
+
+pub fn envelope(operacao: Operacao, retorno: &str) -> String {
+    use crate::Operacao::*;
+    let servico = match operacao {
+        ConsStatServ => "NFeStatusServico4",
+        ConsSitNfe => "NFeConsultaProtocolo4",
+        ConsReciNfe => "NFeRetAutorizacao4",
+        ConsCad => "CadConsultaCadastro4",
+        EnviNfe => "NFeAutorizacao4",
+        EnvEvento => "NFeRecepcaoEvento4",
+        InutNfe => "NFeInutilizacao4",
+        DistDfeInt => "NFeDistribuicaoDFe",
+    };
+    // Assim como na requisição, o retorno da distribuição é envolvido pela operação.
+    let corpo = if operacao == DistDfeInt {
+        format!(
+            concat!(
+                "<nfeDistDFeInteresseResponse xmlns=\"{portal}/wsdl/{servico}\">",
+                "<nfeDistDFeInteresseResult>{retorno}</nfeDistDFeInteresseResult>",
+                "</nfeDistDFeInteresseResponse>"
+            ),
+            portal = PORTAL_FISCAL,
+            servico = servico,
+            retorno = retorno
+        )
+    } else {
+        format!(
+            "<nfeResultMsg xmlns=\"{portal}/wsdl/{servico}\">{retorno}</nfeResultMsg>",
+            portal = PORTAL_FISCAL,
+            servico = servico,
+            retorno = retorno
+        )
+    };
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\">",
+            "<soap:Body>{corpo}</soap:Body>",
+            "</soap:Envelope>"
+        ),
+        corpo = corpo
+    )
+}
+
+pub fn soap_fault(codigo: &str, motivo: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\">",
+            "<soap:Body>",
+            "<soap:Fault>",
+            "<soap:Code><soap:Value>{codigo}</soap:Value></soap:Code>",
+            "<soap:Reason><soap:Text xml:lang=\"pt-BR\">{motivo}</soap:Text></soap:Reason>",
+            "</soap:Fault>",
+            "</soap:Body>",
+            "</soap:Envelope>"
+        ),
+        codigo = codigo,
+        motivo = motivo
+    )
+}
+
+pub fn ret_cons_stat_serv(tp_amb: &str, c_stat: u16, x_motivo: &str, c_uf: &str) -> String {
+    format!(
+        concat!(
+            "<retConsStatServ xmlns=\"{portal}\" versao=\"4.00\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "<cUF>{c_uf}</cUF>",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "<tMed>1</tMed>",
+            "</retConsStatServ>"
+        ),
+        portal = PORTAL_FISCAL,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        c_uf = c_uf,
+        dh_recbto = DH_RECBTO
+    )
+}
+
+pub fn prot_nfe(
+    tp_amb: &str,
+    chave: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    n_prot: Option<&str>,
+) -> String {
+    format!(
+        concat!(
+            "<protNFe versao=\"4.00\">",
+            "<infProt>",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<chNFe>{chave}</chNFe>",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "{n_prot}",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "</infProt>",
+            "</protNFe>"
+        ),
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        chave = chave,
+        dh_recbto = DH_RECBTO,
+        n_prot = opcional("nProt", n_prot),
+        c_stat = c_stat,
+        x_motivo = x_motivo
+    )
+}
+
+pub fn ret_cons_sit_nfe(
+    tp_amb: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    c_uf: &str,
+    chave: &str,
+    prot: &str,
+) -> String {
+    format!(
+        concat!(
+            "<retConsSitNFe xmlns=\"{portal}\" versao=\"4.00\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "<cUF>{c_uf}</cUF>",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "<chNFe>{chave}</chNFe>",
+            "{prot}",
+            "</retConsSitNFe>"
+        ),
+        portal = PORTAL_FISCAL,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        c_uf = c_uf,
+        dh_recbto = DH_RECBTO,
+        chave = chave,
+        prot = prot
+    )
+}
+
+pub fn ret_cons_reci_nfe(
+    tp_amb: &str,
+    n_rec: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    c_uf: &str,
+    prot: &str,
+) -> String {
+    format!(
+        concat!(
+            "<retConsReciNFe xmlns=\"{portal}\" versao=\"4.00\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<nRec>{n_rec}</nRec>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "<cUF>{c_uf}</cUF>",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "{prot}",
+            "</retConsReciNFe>"
+        ),
+        portal = PORTAL_FISCAL,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        n_rec = n_rec,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        c_uf = c_uf,
+        dh_recbto = DH_RECBTO,
+        prot = prot
+    )
+}
+
+pub fn inf_cad(uf: &str, documento: &str) -> String {
+    format!(
+        concat!(
+            "<infCad>",
+            "<IE>123456789</IE>",
+            "{documento}",
+            "<UF>{uf}</UF>",
+            "<cSit>1</cSit>",
+            "<indCredNFe>1</indCredNFe>",
+            "<indCredCTe>4</indCredCTe>",
+            "<xNome>EMPRESA MOCK</xNome>",
+            "<xRegApur>NORMAL</xRegApur>",
+            "<CNAE>6201501</CNAE>",
+            "<dIniAtiv>2000-01-01</dIniAtiv>",
+            "<ender>",
+            "<xLgr>RUA DOS TESTES</xLgr>",
+            "<nro>1</nro>",
+            "<xBairro>CENTRO</xBairro>",
+            "<cMun>5103403</cMun>",
+            "<xMun>CUIABA</xMun>",
+            "<CEP>78000000</CEP>",
+            "</ender>",
+            "</infCad>"
+        ),
+        documento = documento,
+        uf = uf
+    )
+}
+
+pub fn ret_cons_cad(
+    c_stat: u16,
+    x_motivo: &str,
+    uf: &str,
+    documento: &str,
+    inf_cad: &str,
+) -> String {
+    format!(
+        concat!(
+            "<retConsCad xmlns=\"{portal}\" versao=\"2.00\">",
+            "<infCons>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "<UF>{uf}</UF>",
+            "{documento}",
+            "<dhCons>{dh_cons}</dhCons>",
+            "<cUF>{uf}</cUF>",
+            "{inf_cad}",
+            "</infCons>",
+            "</retConsCad>"
+        ),
+        portal = PORTAL_FISCAL,
+        ver_aplic = VER_APLIC,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        uf = uf,
+        documento = documento,
+        dh_cons = DH_RECBTO,
+        inf_cad = inf_cad
+    )
+}
+
+pub fn inf_rec(n_rec: &str) -> String {
+    format!(
+        "<infRec><nRec>{n_rec}</nRec><tMed>1</tMed></infRec>",
+        n_rec = n_rec
+    )
+}
+
+pub fn ret_envi_nfe(
+    tp_amb: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    c_uf: &str,
+    conteudo: &str,
+) -> String {
+    format!(
+        concat!(
+            "<retEnviNFe xmlns=\"{portal}\" versao=\"4.00\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "<cUF>{c_uf}</cUF>",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "{conteudo}",
+            "</retEnviNFe>"
+        ),
+        portal = PORTAL_FISCAL,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        c_uf = c_uf,
+        dh_recbto = DH_RECBTO,
+        conteudo = conteudo
+    )
+}
+
+pub fn ret_evento(
+    tp_amb: &str,
+    c_orgao: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    campos: &str,
+    n_prot: Option<&str>,
+) -> String {
+    format!(
+        concat!(
+            "<retEvento versao=\"1.00\">",
+            "<infEvento>",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cOrgao>{c_orgao}</cOrgao>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "{campos}",
+            "<dhRegEvento>{dh_reg_evento}</dhRegEvento>",
+            "{n_prot}",
+            "</infEvento>",
+            "</retEvento>"
+        ),
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_orgao = c_orgao,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        campos = campos,
+        dh_reg_evento = DH_RECBTO,
+        n_prot = opcional("nProt", n_prot)
+    )
+}
+
+pub fn ret_env_evento(id_lote: &str, tp_amb: &str, c_orgao: &str, ret_eventos: &str) -> String {
+    format!(
+        concat!(
+            "<retEnvEvento xmlns=\"{portal}\" versao=\"1.00\">",
+            "<idLote>{id_lote}</idLote>",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cOrgao>{c_orgao}</cOrgao>",
+            "<cStat>128</cStat>",
+            "<xMotivo>Lote de evento processado</xMotivo>",
+            "{ret_eventos}",
+            "</retEnvEvento>"
+        ),
+        portal = PORTAL_FISCAL,
+        id_lote = id_lote,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_orgao = c_orgao,
+        ret_eventos = ret_eventos
+    )
+}
+
+pub fn ret_inut_nfe(
+    tp_amb: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    campos: &str,
+    n_prot: Option<&str>,
+) -> String {
+    format!(
+        concat!(
+            "<retInutNFe xmlns=\"{portal}\" versao=\"4.00\">",
+            "<infInut>",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "{campos}",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "{n_prot}",
+            "</infInut>",
+            "</retInutNFe>"
+        ),
+        portal = PORTAL_FISCAL,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        campos = campos,
+        dh_recbto = DH_RECBTO,
+        n_prot = opcional("nProt", n_prot)
+    )
+}
+
+/// Resumo de NF-e compactado como na distribuição de DF-e.
+pub fn doc_zip_res_nfe(nsu: u64, chave: &str, n_prot: &str) -> String {
+    let res_nfe = format!(
+        concat!(
+            "<resNFe xmlns=\"{portal}\" versao=\"1.01\">",
+            "<chNFe>{chave}</chNFe>",
+            "<CNPJ>{cnpj}</CNPJ>",
+            "<xNome>EMPRESA MOCK</xNome>",
+            "<IE>123456789</IE>",
+            "<dhEmi>{dh_recbto}</dhEmi>",
+            "<tpNF>1</tpNF>",
+            "<vNF>100.00</vNF>",
+            "<digVal>bW9jaw==</digVal>",
+            "<dhRecbto>{dh_recbto}</dhRecbto>",
+            "<nProt>{n_prot}</nProt>",
+            "<cSitNFe>1</cSitNFe>",
+            "</resNFe>"
+        ),
+        portal = PORTAL_FISCAL,
+        chave = chave,
+        cnpj = CNPJ,
+        dh_recbto = DH_RECBTO,
+        n_prot = n_prot
+    );
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    // Escrita em memória não falha.
+    let _ = gz.write_all(res_nfe.as_bytes());
+    let zip = gz.finish().unwrap_or_default();
+    format!(
+        concat!(
+            "<loteDistDFeInt>",
+            "<docZip NSU=\"{nsu:015}\" schema=\"resNFe_v1.01.xsd\">{zip}</docZip>",
+            "</loteDistDFeInt>"
+        ),
+        nsu = nsu,
+        zip = BASE64.encode(zip),
+    )
+}
+
+pub fn ret_dist_dfe_int(
+    tp_amb: &str,
+    c_stat: u16,
+    x_motivo: &str,
+    ult_nsu: u64,
+    max_nsu: u64,
+    lote: &str,
+) -> String {
+    format!(
+        concat!(
+            "<retDistDFeInt xmlns=\"{portal}\" versao=\"1.01\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<cStat>{c_stat}</cStat>",
+            "<xMotivo>{x_motivo}</xMotivo>",
+            "<dhResp>{dh_resp}</dhResp>",
+            "<ultNSU>{ult_nsu:015}</ultNSU>",
+            "<maxNSU>{max_nsu:015}</maxNSU>",
+            "{lote}",
+            "</retDistDFeInt>"
+        ),
+        portal = PORTAL_FISCAL,
+        tp_amb = tp_amb,
+        ver_aplic = VER_APLIC,
+        c_stat = c_stat,
+        x_motivo = x_motivo,
+        dh_resp = DH_RECBTO,
+        ult_nsu = ult_nsu,
+        max_nsu = max_nsu,
+        lote = lote
+    )
+}
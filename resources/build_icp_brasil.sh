@@ -0,0 +1,18 @@
+#!/bin/sh
+
+# Extrai a cadeia de certificados da AC Raiz e das ACs intermediárias da ICP-Brasil, do pacote
+# compactado publicado no repositório do ITI, para o diretório `icp_brasil`, embutido na biblioteca
+# pela feature `embed_icp_brasil`.
+#
+# Uso: ./build_icp_brasil.sh ACcompactado.zip
+
+set -e
+
+if [ -z "$1" ]; then
+    echo "Uso: $0 ACcompactado.zip" >&2
+    exit 1
+fi
+
+rm -rf icp_brasil
+mkdir icp_brasil
+unzip -j -o "$1" '*.crt' '*.cer' '*.pem' -d icp_brasil || [ $? -eq 11 ]
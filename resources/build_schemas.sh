@@ -0,0 +1,17 @@
+#!/bin/sh
+
+# Extrai os schemas XSD do pacote de liberação da NF-e (PL_009), baixado do Portal da NF-e, para o
+# diretório `schemas`, embutido na biblioteca pela feature `embed_schemas`.
+#
+# Uso: ./build_schemas.sh PL_009_V4.zip
+
+set -e
+
+if [ -z "$1" ]; then
+    echo "Uso: $0 PL_009_V4.zip" >&2
+    exit 1
+fi
+
+rm -rf schemas
+mkdir schemas
+unzip -j -o "$1" '*.xsd' -d schemas
@@ -0,0 +1,663 @@
+use std::{collections::BTreeMap, error::Error as StdError, result};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use roxmltree::{Document, Node, NodeType};
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Sign, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+use crate::Pkcs12Certificate;
+
+/// Namespace de assinaturas XMLDSig.
+pub const XMLDSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+/// Algoritmo de canonicalização C14N exigido pela SEFAZ.
+pub const C14N: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// OID ICP-Brasil do CNPJ da pessoa jurídica titular do certificado (e-CNPJ).
+const OID_CNPJ: &str = "2.16.76.1.3.3";
+
+/// OID ICP-Brasil dos dados da pessoa física titular do certificado (e-CPF).
+const OID_CPF: &str = "2.16.76.1.3.1";
+
+/// Tipo para tratar erros relacionados a assinatura de XML.
+#[derive(Error, Debug)]
+pub enum AssinaturaError {
+    /// Erros relacionados a XML mal formado.
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    /// Erros relacionados a geração da assinatura RSA.
+    #[error(transparent)]
+    Rsa(#[from] rsa::Error),
+    /// Erros relacionados a decodificação de conteúdo base64.
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    /// Nenhum elemento com o atributo `Id` informado foi encontrado.
+    #[error("Elemento não encontrado para o Id: {0}")]
+    ElementoNaoEncontrado(String),
+    /// Mais de um elemento possui o atributo `Id` informado.
+    #[error("Id duplicado: {0}")]
+    IdDuplicado(String),
+    /// Elemento referenciado pela assinatura não é irmão da tag `Signature`, o que permite
+    /// adulterações por _signature wrapping_.
+    #[error("Elemento assinado fora da posição esperada para o Id: {0}")]
+    ReferenciaInvalida(String),
+    /// XML não possui assinatura.
+    #[error("Assinatura não encontrada")]
+    AssinaturaNaoEncontrada,
+    /// Tag obrigatória da assinatura não encontrada.
+    #[error("Tag da assinatura não encontrada: {0}")]
+    TagNaoEncontrada(&'static str),
+    /// Algoritmo de canonicalização, digest ou assinatura diferente dos exigidos pela SEFAZ.
+    #[error("Algoritmo não suportado: {0}")]
+    AlgoritmoNaoSuportado(String),
+    /// Certificado X.509 da assinatura ilegível ou sem chave pública RSA.
+    #[error("Certificado inválido: {0}")]
+    CertificadoInvalido(String),
+    /// Digest do conteúdo assinado difere do informado na assinatura, i.e. XML adulterado.
+    #[error("Digest inválido para o Id: {0}")]
+    DigestInvalido(String),
+    /// Valor da assinatura não confere com o certificado informado.
+    #[error("Assinatura inválida")]
+    AssinaturaInvalida,
+    /// Erros emitidos por assinadores externos, e.g. tokens e cartões A3.
+    #[error(transparent)]
+    Externo(Box<dyn StdError + Send + Sync>),
+}
+
+/// Tipo para tratar retorno de assinatura de XML.
+pub type AssinaturaResult = result::Result<String, AssinaturaError>;
+
+/// Assinador de XMLs enviados à SEFAZ.
+///
+/// Permite usar certificados armazenados fora do processo, como tokens e cartões A3, para assinar
+/// eventos, lotes e pedidos de inutilização.
+pub trait Assinador: Send + Sync {
+    /// Assina elemento identificado pelo atributo `Id` informado, retornando XML com a tag
+    /// `Signature` inserida logo após o elemento assinado.
+    fn assinar(&self, xml: &str, id: &str) -> AssinaturaResult;
+}
+
+impl Assinador for Pkcs12Certificate {
+    fn assinar(&self, xml: &str, id: &str) -> AssinaturaResult {
+        let doc = Document::parse(xml)?;
+        let elemento = localizar(&doc, id)?;
+        let digest = BASE64.encode(Sha1::digest(canonicalizar_no(elemento, None)));
+        let signed_info = format_signed_info(id, &digest);
+        // O SignedInfo é assinado em sua forma canônica já inserido no documento, pois herda os
+        // namespaces em vigor na tag Signature, e.g. `xmlns:xsi` declarado em um ancestral.
+        let fim = elemento.range().end;
+        let provisorio = format!(
+            "{}{}{}",
+            &xml[..fim],
+            format_signature(&signed_info, "", ""),
+            &xml[fim..]
+        );
+        let doc = Document::parse(&provisorio)?;
+        let signature = doc
+            .descendants()
+            .find(|node| node.range().start == fim && e_xmldsig(*node, "Signature"))
+            .ok_or(AssinaturaError::AssinaturaNaoEncontrada)?;
+        let canonico = canonicalizar_no(filho_xmldsig(signature, "SignedInfo")?, None);
+        let assinatura = self.chave().sign(
+            Pkcs1v15Sign::new::<Sha1>(),
+            &Sha1::digest(canonico.as_bytes()),
+        )?;
+        let signature = format_signature(
+            &signed_info,
+            &BASE64.encode(assinatura),
+            &BASE64.encode(self.certificado()),
+        );
+        Ok(format!("{}{}{}", &xml[..fim], signature, &xml[fim..]))
+    }
+}
+
+/// Titular do certificado usado para assinar um XML.
+#[derive(Clone, Debug)]
+pub struct Assinante {
+    /// Nome comum (CN) do titular.
+    pub nome: String,
+    /// CNPJ do titular, quando certificado e-CNPJ.
+    pub cnpj: Option<String>,
+    /// CPF do titular, quando certificado e-CPF.
+    pub cpf: Option<String>,
+    /// Nome comum (CN) da autoridade certificadora emissora.
+    pub emissor: String,
+    /// Número de série do certificado em hexadecimal.
+    pub numero_serie: String,
+    pub valido_de: DateTime<Utc>,
+    pub valido_ate: DateTime<Utc>,
+    /// Certificado X.509 codificado em DER.
+    pub certificado: Vec<u8>,
+}
+
+impl Assinante {
+    /// Verifica se o certificado está dentro do período de validade.
+    pub fn vigente(&self) -> bool {
+        let agora = Utc::now();
+        self.valido_de <= agora && agora <= self.valido_ate
+    }
+
+    fn from_der(der: &[u8]) -> result::Result<Self, AssinaturaError> {
+        let (_, cert) = X509Certificate::from_der(der)
+            .map_err(|err| AssinaturaError::CertificadoInvalido(err.to_string()))?;
+        let nome_comum = |nome: &x509_parser::x509::X509Name| {
+            nome.iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let nome = nome_comum(cert.subject());
+        let mut cnpj = None;
+        let mut cpf = None;
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for nome_alternativo in &san.value.general_names {
+                if let GeneralName::OtherName(oid, valor) = nome_alternativo {
+                    let valor = valor_outro_nome(valor);
+                    match oid.to_id_string().as_str() {
+                        OID_CNPJ => cnpj = valor.map(str::to_string),
+                        // Data de nascimento (8 dígitos) seguida do CPF (11 dígitos).
+                        OID_CPF => cpf = valor.and_then(|v| v.get(8..19)).map(str::to_string),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        // Certificados ICP-Brasil também trazem o documento no CN, e.g. "EMPRESA LTDA:12345678000195".
+        if cnpj.is_none() && cpf.is_none() {
+            match nome.rsplit_once(':').map(|(_, doc)| doc) {
+                Some(doc) if doc.len() == 14 && doc.bytes().all(|b| b.is_ascii_digit()) => {
+                    cnpj = Some(doc.to_string())
+                }
+                Some(doc) if doc.len() == 11 && doc.bytes().all(|b| b.is_ascii_digit()) => {
+                    cpf = Some(doc.to_string())
+                }
+                _ => {}
+            }
+        }
+        let data = |timestamp| DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+        Ok(Self {
+            nome,
+            cnpj,
+            cpf,
+            emissor: nome_comum(cert.issuer()),
+            numero_serie: cert.raw_serial_as_string(),
+            valido_de: data(cert.validity().not_before.timestamp()),
+            valido_ate: data(cert.validity().not_after.timestamp()),
+            certificado: der.to_vec(),
+        })
+    }
+}
+
+/// Verifica a primeira assinatura do XML, e.g. da NF-e em um `nfeProc` ou do evento em um
+/// `procEventoNFe`, conferindo o digest do elemento referenciado e o valor da assinatura com o
+/// certificado embutido, e retorna o titular desse certificado.
+///
+/// Como no padrão da SEFAZ, o elemento referenciado deve ser irmão da tag `Signature` e ter `Id`
+/// único no documento, e as transformações devem ser exatamente _enveloped signature_ e C14N.
+///
+/// A validade do certificado não é verificada, use [`Assinante::vigente`] para isso.
+pub fn verificar_assinatura(xml: &str) -> result::Result<Assinante, AssinaturaError> {
+    let doc = Document::parse(xml)?;
+    let signature = doc
+        .descendants()
+        .find(|node| e_xmldsig(*node, "Signature"))
+        .ok_or(AssinaturaError::AssinaturaNaoEncontrada)?;
+    let signed_info = filho_xmldsig(signature, "SignedInfo")?;
+    verificar_algoritmo(filho_xmldsig(signed_info, "CanonicalizationMethod")?, C14N)?;
+    let signature_method = filho_xmldsig(signed_info, "SignatureMethod")?;
+    verificar_algoritmo(signature_method, &format!("{}rsa-sha1", XMLDSIG))?;
+    let reference = filho_xmldsig(signed_info, "Reference")?;
+    verificar_transformacoes(filho_xmldsig(reference, "Transforms")?)?;
+    verificar_algoritmo(
+        filho_xmldsig(reference, "DigestMethod")?,
+        &format!("{}sha1", XMLDSIG),
+    )?;
+    let uri = reference.attribute("URI").unwrap_or_default();
+    let elemento = match uri.strip_prefix('#') {
+        Some(id) => localizar(&doc, id)?,
+        None if uri.is_empty() => doc.root_element(),
+        None => return Err(AssinaturaError::ElementoNaoEncontrado(uri.to_string())),
+    };
+    // Impede que o elemento assinado original seja movido para outra posição do documento enquanto
+    // um elemento forjado ocupa o seu lugar junto à assinatura.
+    if !uri.is_empty() && elemento.parent() != signature.parent() {
+        return Err(AssinaturaError::ReferenciaInvalida(uri.to_string()));
+    }
+    let digest = decodificar(filho_xmldsig(reference, "DigestValue")?)?;
+    if Sha1::digest(canonicalizar_no(elemento, Some(signature))).as_slice() != digest {
+        return Err(AssinaturaError::DigestInvalido(uri.to_string()));
+    }
+    let certificado = filho_xmldsig(signature, "KeyInfo")
+        .and_then(|key_info| filho_xmldsig(key_info, "X509Data"))
+        .and_then(|x509_data| filho_xmldsig(x509_data, "X509Certificate"))?;
+    let assinante = Assinante::from_der(&decodificar(certificado)?)?;
+    let (_, cert) = X509Certificate::from_der(&assinante.certificado)
+        .map_err(|err| AssinaturaError::CertificadoInvalido(err.to_string()))?;
+    let chave = RsaPublicKey::from_public_key_der(cert.public_key().raw)
+        .map_err(|err| AssinaturaError::CertificadoInvalido(err.to_string()))?;
+    let assinatura = decodificar(filho_xmldsig(signature, "SignatureValue")?)?;
+    chave
+        .verify(
+            Pkcs1v15Sign::new::<Sha1>(),
+            &Sha1::digest(canonicalizar_no(signed_info, None)),
+            &assinatura,
+        )
+        .map_err(|_| AssinaturaError::AssinaturaInvalida)?;
+    Ok(assinante)
+}
+
+/// Canonicaliza elemento identificado pelo atributo `Id` usando C14N, desconsiderando a assinatura
+/// contida nele que o referencia (transformação _enveloped signature_).
+pub fn canonicalizar(xml: &str, id: &str) -> AssinaturaResult {
+    let doc = Document::parse(xml)?;
+    let elemento = localizar(&doc, id)?;
+    let referencia = format!("#{}", id);
+    let envelopada = elemento.descendants().find(|node| {
+        e_xmldsig(*node, "Signature")
+            && filho_xmldsig(*node, "SignedInfo")
+                .and_then(|signed_info| filho_xmldsig(signed_info, "Reference"))
+                .is_ok_and(|reference| reference.attribute("URI") == Some(referencia.as_str()))
+    });
+    Ok(canonicalizar_no(elemento, envelopada))
+}
+
+/// Localiza o único elemento com o atributo `Id` informado.
+#[inline]
+pub(crate) fn localizar<'a, 'input>(
+    doc: &'a Document<'input>,
+    id: &str,
+) -> result::Result<Node<'a, 'input>, AssinaturaError> {
+    let mut elementos = doc
+        .descendants()
+        .filter(|node| node.attribute("Id") == Some(id));
+    let elemento = elementos
+        .next()
+        .ok_or_else(|| AssinaturaError::ElementoNaoEncontrado(id.to_string()))?;
+    if elementos.next().is_some() {
+        return Err(AssinaturaError::IdDuplicado(id.to_string()));
+    }
+    Ok(elemento)
+}
+
+/// Canonicaliza elemento usando C14N, removendo a assinatura informada (transformação _enveloped
+/// signature_).
+#[inline]
+pub(crate) fn canonicalizar_no(node: Node, assinatura: Option<Node>) -> String {
+    let mut saida = String::new();
+    escrever_elemento(node, &BTreeMap::new(), assinatura, &mut saida);
+    saida
+}
+
+#[inline]
+fn e_xmldsig(node: Node, nome: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(XMLDSIG)
+        && node.tag_name().name() == nome
+}
+
+#[inline]
+fn filho_xmldsig<'a, 'input>(
+    node: Node<'a, 'input>,
+    nome: &'static str,
+) -> result::Result<Node<'a, 'input>, AssinaturaError> {
+    node.children()
+        .find(|filho| e_xmldsig(*filho, nome))
+        .ok_or(AssinaturaError::TagNaoEncontrada(nome))
+}
+
+#[inline]
+fn verificar_algoritmo(node: Node, esperado: &str) -> result::Result<(), AssinaturaError> {
+    match node.attribute("Algorithm") {
+        Some(algoritmo) if algoritmo == esperado => Ok(()),
+        algoritmo => Err(AssinaturaError::AlgoritmoNaoSuportado(
+            algoritmo.unwrap_or_default().to_string(),
+        )),
+    }
+}
+
+/// Verifica se as transformações são exatamente _enveloped signature_ seguida de C14N.
+#[inline]
+fn verificar_transformacoes(transforms: Node) -> result::Result<(), AssinaturaError> {
+    let mut transforms = transforms
+        .children()
+        .filter(|filho| e_xmldsig(*filho, "Transform"));
+    let enveloped = format!("{}enveloped-signature", XMLDSIG);
+    for esperado in [enveloped.as_str(), C14N] {
+        verificar_algoritmo(
+            transforms
+                .next()
+                .ok_or(AssinaturaError::TagNaoEncontrada("Transform"))?,
+            esperado,
+        )?;
+    }
+    match transforms.next() {
+        Some(transform) => Err(AssinaturaError::AlgoritmoNaoSuportado(
+            transform
+                .attribute("Algorithm")
+                .unwrap_or_default()
+                .to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+#[inline]
+fn decodificar(node: Node) -> result::Result<Vec<u8>, AssinaturaError> {
+    // Valores base64 de XMLs recebidos costumam vir quebrados em linhas.
+    let valor: String = node
+        .text()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    Ok(BASE64.decode(valor)?)
+}
+
+/// Extrai o valor de um `otherName` ICP-Brasil, codificado como `[0] EXPLICIT` contendo uma
+/// string DER de tamanho curto.
+#[inline]
+fn valor_outro_nome(der: &[u8]) -> Option<&str> {
+    match der {
+        [0xa0, _, _, tamanho, valor @ ..] if usize::from(*tamanho) == valor.len() => {
+            std::str::from_utf8(valor).ok()
+        }
+        _ => None,
+    }
+}
+
+#[inline]
+fn format_signed_info(id: &str, digest: &str) -> String {
+    format!(
+        concat!(
+            "<SignedInfo>",
+            "<CanonicalizationMethod Algorithm=\"{c14n}\"></CanonicalizationMethod>",
+            "<SignatureMethod Algorithm=\"{xmldsig}rsa-sha1\"></SignatureMethod>",
+            "<Reference URI=\"#{id}\">",
+            "<Transforms>",
+            "<Transform Algorithm=\"{xmldsig}enveloped-signature\"></Transform>",
+            "<Transform Algorithm=\"{c14n}\"></Transform>",
+            "</Transforms>",
+            "<DigestMethod Algorithm=\"{xmldsig}sha1\"></DigestMethod>",
+            "<DigestValue>{digest}</DigestValue>",
+            "</Reference>",
+            "</SignedInfo>"
+        ),
+        c14n = C14N,
+        xmldsig = XMLDSIG,
+        id = id,
+        digest = digest,
+    )
+}
+
+#[inline]
+fn format_signature(signed_info: &str, assinatura: &str, certificado: &str) -> String {
+    format!(
+        concat!(
+            "<Signature xmlns=\"{xmldsig}\">",
+            "{signed_info}",
+            "<SignatureValue>{assinatura}</SignatureValue>",
+            "<KeyInfo>",
+            "<X509Data>",
+            "<X509Certificate>{certificado}</X509Certificate>",
+            "</X509Data>",
+            "</KeyInfo>",
+            "</Signature>"
+        ),
+        xmldsig = XMLDSIG,
+        signed_info = signed_info,
+        assinatura = assinatura,
+        certificado = certificado,
+    )
+}
+
+/// Escreve elemento em forma canônica, emitindo apenas declarações de namespace que ainda não
+/// estão em vigor no elemento pai já escrito.
+fn escrever_elemento(
+    node: Node,
+    ns_pai: &BTreeMap<&str, &str>,
+    assinatura: Option<Node>,
+    saida: &mut String,
+) {
+    let mut ns = BTreeMap::new();
+    for namespace in node.namespaces() {
+        if namespace.uri() != XML_NAMESPACE {
+            ns.insert(namespace.name().unwrap_or_default(), namespace.uri());
+        }
+    }
+    let nome = nome_qualificado(node, node.tag_name().namespace(), node.tag_name().name());
+    saida.push('<');
+    saida.push_str(&nome);
+    // xmlns="" só é emitido quando desfaz um namespace padrão herdado.
+    if !ns.contains_key("") && ns_pai.get("").is_some_and(|uri| !uri.is_empty()) {
+        saida.push_str(" xmlns=\"\"");
+    }
+    for (prefixo, uri) in &ns {
+        if ns_pai.get(prefixo) != Some(uri) {
+            if prefixo.is_empty() {
+                saida.push_str(" xmlns=\"");
+            } else {
+                saida.push_str(" xmlns:");
+                saida.push_str(prefixo);
+                saida.push_str("=\"");
+            }
+            saida.push_str(&escapar_atributo(uri));
+            saida.push('"');
+        }
+    }
+    let mut atributos: Vec<_> = node.attributes().collect();
+    atributos.sort_by_key(|atributo| (atributo.namespace().unwrap_or_default(), atributo.name()));
+    for atributo in atributos {
+        saida.push(' ');
+        saida.push_str(&nome_qualificado(
+            node,
+            atributo.namespace(),
+            atributo.name(),
+        ));
+        saida.push_str("=\"");
+        saida.push_str(&escapar_atributo(atributo.value()));
+        saida.push('"');
+    }
+    saida.push('>');
+    for filho in node.children() {
+        match filho.node_type() {
+            // Transformação enveloped signature: apenas a própria assinatura é ignorada.
+            NodeType::Element if Some(filho) != assinatura => {
+                escrever_elemento(filho, &ns, assinatura, saida)
+            }
+            NodeType::Text => saida.push_str(&escapar_texto(filho.text().unwrap_or_default())),
+            _ => {}
+        }
+    }
+    saida.push_str("</");
+    saida.push_str(&nome);
+    saida.push('>');
+}
+
+#[inline]
+fn nome_qualificado(node: Node, namespace: Option<&str>, nome: &str) -> String {
+    match namespace.and_then(|uri| node.lookup_prefix(uri)) {
+        Some(prefixo) if !prefixo.is_empty() => format!("{}:{}", prefixo, nome),
+        _ => nome.to_string(),
+    }
+}
+
+#[inline]
+fn escapar_texto(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\r', "&#xD;")
+}
+
+#[inline]
+fn escapar_atributo(valor: &str) -> String {
+    valor
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+        .replace('\t', "&#x9;")
+        .replace('\n', "&#xA;")
+        .replace('\r', "&#xD;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkcs12() -> Pkcs12Certificate {
+        Pkcs12Certificate::from_bytes(fiscalidade_mock::PKCS12, fiscalidade_mock::SENHA_PKCS12)
+            .unwrap()
+    }
+
+    #[test]
+    fn assinar() {
+        let xml = concat!(
+            "<envEvento xmlns=\"http://www.portalfiscal.inf.br/nfe\" ",
+            "xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">",
+            "<evento><infEvento Id=\"ID1\"><tpAmb>2</tpAmb></infEvento></evento>",
+            "</envEvento>"
+        );
+        let assinado = pkcs12().assinar(xml, "ID1").unwrap();
+        // Signature é inserida logo após o elemento assinado, dentro do mesmo pai.
+        assert!(assinado.contains(
+            "</infEvento><Signature xmlns=\"http://www.w3.org/2000/09/xmldsig#\"><SignedInfo>"
+        ));
+        assert!(assinado.ends_with("</Signature></evento></envEvento>"));
+        assert!(assinado.contains("<Reference URI=\"#ID1\">"));
+
+        // Em sua forma canônica, o SignedInfo declara também os namespaces herdados do lote.
+        let doc = Document::parse(&assinado).unwrap();
+        let signature = doc
+            .descendants()
+            .find(|node| e_xmldsig(*node, "Signature"))
+            .unwrap();
+        let canonico = canonicalizar_no(filho_xmldsig(signature, "SignedInfo").unwrap(), None);
+        assert!(canonico.starts_with(concat!(
+            "<SignedInfo xmlns=\"http://www.w3.org/2000/09/xmldsig#\" ",
+            "xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">"
+        )));
+        assert!(verificar_assinatura(&assinado).is_ok());
+    }
+
+    #[test]
+    fn assinar_id_invalido() {
+        let xml = "<evento><infEvento Id=\"ID1\"/><infEvento Id=\"ID1\"/></evento>";
+        assert!(matches!(
+            pkcs12().assinar(xml, "ID2"),
+            Err(AssinaturaError::ElementoNaoEncontrado(id)) if id == "ID2"
+        ));
+        assert!(matches!(
+            pkcs12().assinar(xml, "ID1"),
+            Err(AssinaturaError::IdDuplicado(id)) if id == "ID1"
+        ));
+    }
+
+    #[test]
+    fn signature() {
+        let signature = format_signature("<SignedInfo></SignedInfo>", "YXNz", "Y2VydA==");
+        assert_eq!(
+            signature,
+            concat!(
+                "<Signature xmlns=\"http://www.w3.org/2000/09/xmldsig#\">",
+                "<SignedInfo></SignedInfo>",
+                "<SignatureValue>YXNz</SignatureValue>",
+                "<KeyInfo><X509Data><X509Certificate>Y2VydA==</X509Certificate></X509Data></KeyInfo>",
+                "</Signature>"
+            )
+        );
+        let signed_info = format_signed_info("ID1", "ZGln");
+        assert!(signed_info.contains("<Reference URI=\"#ID1\">"));
+        assert!(signed_info.contains("<DigestValue>ZGln</DigestValue>"));
+    }
+
+    #[test]
+    fn transformacoes() {
+        let transforms = |algoritmos: &[&str]| {
+            let xml = format!(
+                "<Transforms xmlns=\"{}\">{}</Transforms>",
+                XMLDSIG,
+                algoritmos
+                    .iter()
+                    .map(|algoritmo| format!("<Transform Algorithm=\"{}\"/>", algoritmo))
+                    .collect::<String>()
+            );
+            verificar_transformacoes(Document::parse(&xml).unwrap().root_element())
+        };
+        let enveloped = format!("{}enveloped-signature", XMLDSIG);
+        assert!(transforms(&[&enveloped, C14N]).is_ok());
+        assert!(matches!(
+            transforms(&[&enveloped]),
+            Err(AssinaturaError::TagNaoEncontrada("Transform"))
+        ));
+        assert!(matches!(
+            transforms(&[C14N, &enveloped]),
+            Err(AssinaturaError::AlgoritmoNaoSuportado(algoritmo)) if algoritmo == C14N
+        ));
+        assert!(matches!(
+            transforms(&[&enveloped, C14N, C14N]),
+            Err(AssinaturaError::AlgoritmoNaoSuportado(_))
+        ));
+    }
+
+    #[test]
+    fn localizar_id() {
+        let doc = Document::parse("<a><b Id=\"1\"/><c><d Id=\"2\"/><e Id=\"2\"/></c></a>").unwrap();
+        assert_eq!(localizar(&doc, "1").unwrap().tag_name().name(), "b");
+        assert!(matches!(
+            localizar(&doc, "2"),
+            Err(AssinaturaError::IdDuplicado(id)) if id == "2"
+        ));
+        assert!(matches!(
+            localizar(&doc, "3"),
+            Err(AssinaturaError::ElementoNaoEncontrado(id)) if id == "3"
+        ));
+    }
+
+    #[test]
+    fn canonicalizar_c14n() {
+        let xml = concat!(
+            "<?xml version=\"1.0\"?>",
+            "<lote xmlns=\"urn:a\" xmlns:x=\"urn:x\">",
+            "<inf Id=\"ID1\" b=\"2\" a=\"1&#9;&lt;\"><v>1 &amp; 2 &gt; 0</v><w/>",
+            "<Signature xmlns=\"http://www.w3.org/2000/09/xmldsig#\"><SignedInfo>",
+            "<Reference URI=\"#ID1\"/></SignedInfo></Signature>",
+            "<s xmlns=\"\"/>",
+            "</inf>",
+            "</lote>"
+        );
+        // Namespaces herdados são declarados, atributos ordenados, tags vazias expandidas e a
+        // assinatura envelopada removida.
+        assert_eq!(
+            canonicalizar(xml, "ID1").unwrap(),
+            concat!(
+                "<inf xmlns=\"urn:a\" xmlns:x=\"urn:x\" Id=\"ID1\" a=\"1&#x9;&lt;\" b=\"2\">",
+                "<v>1 &amp; 2 &gt; 0</v><w></w><s xmlns=\"\"></s>",
+                "</inf>"
+            )
+        );
+        // Assinatura que referencia outro elemento é mantida.
+        assert!(canonicalizar(&xml.replace("#ID1", "#ID2"), "ID1")
+            .unwrap()
+            .contains("<Signature xmlns=\"http://www.w3.org/2000/09/xmldsig#\">"));
+    }
+
+    #[test]
+    fn outro_nome() {
+        assert_eq!(
+            valor_outro_nome(&[0xa0, 0x10, 0x04, 0x02, b'4', b'2']),
+            Some("42")
+        );
+        assert_eq!(
+            valor_outro_nome(&[0xa0, 0x10, 0x04, 0x03, b'4', b'2']),
+            None
+        );
+        assert_eq!(valor_outro_nome(&[0x30, 0x10, 0x04, 0x00]), None);
+    }
+}
@@ -1,7 +1,14 @@
-use std::{result, time::Duration};
+use std::{error::Error as StdError, io, result, str, time::Duration};
 
-use reqwest::{Client as HttpClient, ClientBuilder as HttpClientBuilder};
+use async_trait::async_trait;
+#[cfg(not(feature = "rustls"))]
+use reqwest::Certificate;
+use reqwest::{
+    Client as HttpClient, ClientBuilder as HttpClientBuilder, NoProxy, Proxy as HttpProxy,
+};
+use roxmltree::{Document, Node};
 use thiserror::Error;
+use tokio::time;
 
 use crate::Pkcs12Certificate;
 
@@ -11,27 +18,271 @@ pub const CLIENT_CONNECT_TIMEOUT: u64 = 5;
 /// Tempo padrão de timeout para transmissão de dados de client HTTP.
 pub const CLIENT_TIMEOUT: u64 = 30;
 
+/// Tempo padrão de espera, em milissegundos, antes da primeira nova tentativa.
+pub const CLIENT_ESPERA_INICIAL: u64 = 500;
+
+/// Tempo padrão máximo de espera, em milissegundos, entre tentativas.
+pub const CLIENT_ESPERA_MAXIMA: u64 = 10_000;
+
+/// Cadeia da AC Raiz e das ACs intermediárias da ICP-Brasil, que emitem os certificados dos
+/// webservices da SEFAZ, confiada por padrão além dos certificados do sistema.
+#[cfg(feature = "embed_icp_brasil")]
+pub(crate) static ICP_BRASIL: &[&[u8]] = include!(concat!(env!("OUT_DIR"), "/icp_brasil.rs"));
+#[cfg(not(feature = "embed_icp_brasil"))]
+pub(crate) static ICP_BRASIL: &[&[u8]] = &[];
+
 /// Tipo para tratar erros relacionados a I/O e ao client HTTP.
 #[derive(Error, Debug)]
 pub enum ClientError {
     /// Erros relacionados a HTTP.
     #[error(transparent)]
     HttpClient(#[from] reqwest::Error),
+    /// Servidor respondeu com status HTTP de erro (4xx ou 5xx).
+    #[error("Erro HTTP {status}")]
+    Http { status: u16, corpo: String },
+    /// Servidor respondeu com SOAP Fault.
+    #[error("SOAP Fault {codigo}: {motivo}")]
+    SoapFault {
+        status: u16,
+        codigo: String,
+        motivo: String,
+        corpo: String,
+    },
+    /// Erros relacionados à configuração TLS do rustls.
+    #[cfg(feature = "rustls")]
+    #[error(transparent)]
+    Tls(#[from] rustls::Error),
+    /// Certificado raiz em PEM inválido.
+    #[cfg(feature = "rustls")]
+    #[error(transparent)]
+    Pem(#[from] rustls::pki_types::pem::Error),
+    /// Erros emitidos por transportes externos.
+    #[error(transparent)]
+    Externo(Box<dyn StdError + Send + Sync>),
+}
+
+impl ClientError {
+    /// Indica se a requisição pode ser repetida: falhas de conexão, timeouts, conexões
+    /// interrompidas e HTTP 502, 503 e 504.
+    pub fn repetivel(&self) -> bool {
+        match self {
+            ClientError::HttpClient(err) => {
+                err.is_connect() || err.is_timeout() || conexao_interrompida(err)
+            }
+            ClientError::Http { status, .. } => matches!(status, 502..=504),
+            ClientError::SoapFault { .. } | ClientError::Externo(_) => false,
+            #[cfg(feature = "rustls")]
+            ClientError::Tls(_) | ClientError::Pem(_) => false,
+        }
+    }
+
+    /// Indica se a requisição com certeza não chegou ao servidor.
+    #[inline]
+    fn nao_enviada(&self) -> bool {
+        matches!(self, ClientError::HttpClient(err) if err.is_connect())
+    }
+}
+
+#[inline]
+fn conexao_interrompida(err: &reqwest::Error) -> bool {
+    let mut origem = err.source();
+    while let Some(err) = origem {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            return matches!(
+                err.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+        origem = err.source();
+    }
+    false
 }
 
 /// Tipo para tratar retorno do client HTTP.
 pub type ClientResult = result::Result<Vec<u8>, ClientError>;
 
+/// Política de novas tentativas de comunicação com o webservice, com espera exponencial e
+/// aleatória entre as tentativas.
+///
+/// Por padrão apenas serviços de consulta são repetidos após falhas que podem ter ocorrido depois
+/// da requisição chegar ao servidor, pois repetir uma autorização ou evento pode gerar duplicidade.
+/// Serviços não idempotentes só são repetidos em falhas de conexão, a menos que habilitado com
+/// [`Tentativas::set_nao_idempotentes`]. Os erros repetidos são definidos por
+/// [`Tentativas::set_repetivel`].
+#[derive(Clone, Copy, Debug)]
+pub struct Tentativas {
+    maximo: u32,
+    espera_inicial: Duration,
+    espera_maxima: Duration,
+    nao_idempotentes: bool,
+    repetivel: fn(&ClientError) -> bool,
+}
+
+impl Tentativas {
+    /// Cria política com a quantidade máxima de tentativas, incluindo a primeira.
+    pub fn new(maximo: u32) -> Self {
+        Self {
+            maximo: maximo.max(1),
+            espera_inicial: Duration::from_millis(CLIENT_ESPERA_INICIAL),
+            espera_maxima: Duration::from_millis(CLIENT_ESPERA_MAXIMA),
+            nao_idempotentes: false,
+            repetivel: ClientError::repetivel,
+        }
+    }
+
+    /// Aplica tempo de espera antes da primeira nova tentativa, dobrado a cada tentativa seguinte.
+    pub fn set_espera_inicial(mut self, espera: Duration) -> Self {
+        self.espera_inicial = espera;
+        self
+    }
+
+    /// Aplica tempo máximo de espera entre tentativas.
+    pub fn set_espera_maxima(mut self, espera: Duration) -> Self {
+        self.espera_maxima = espera;
+        self
+    }
+
+    /// Permite repetir serviços não idempotentes, como autorização, eventos e inutilização.
+    pub fn set_nao_idempotentes(mut self, nao_idempotentes: bool) -> Self {
+        self.nao_idempotentes = nao_idempotentes;
+        self
+    }
+
+    /// Aplica critério para identificar erros que podem ser repetidos, e.g. incluir HTTP 500 ou
+    /// excluir timeouts. Por padrão, usa [`ClientError::repetivel`].
+    pub fn set_repetivel(mut self, repetivel: fn(&ClientError) -> bool) -> Self {
+        self.repetivel = repetivel;
+        self
+    }
+
+    #[inline]
+    fn espera(&self, tentativa: u32) -> Duration {
+        let espera = self
+            .espera_inicial
+            .saturating_mul(2u32.saturating_pow(tentativa.saturating_sub(1)))
+            .min(self.espera_maxima);
+        // Espera aleatória entre metade e o total, evitando que vários clients repitam juntos.
+        espera / 2 + espera.mul_f64(fastrand::f64() / 2.0)
+    }
+}
+
+impl Default for Tentativas {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Proxy HTTP, HTTPS ou SOCKS5 usado para acessar os webservices.
+#[derive(Clone, Debug)]
+pub struct Proxy {
+    url: String,
+    credenciais: Option<(String, String)>,
+    excecoes: Option<String>,
+}
+
+impl Proxy {
+    /// Cria proxy a partir de URL, e.g. `http://proxy:3128` ou `socks5://proxy:1080`.
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self {
+            url: url.into(),
+            credenciais: None,
+            excecoes: None,
+        }
+    }
+
+    /// Aplica usuário e senha para autenticação no proxy.
+    pub fn set_credenciais<S: Into<String>>(mut self, usuario: S, senha: S) -> Self {
+        self.credenciais = Some((usuario.into(), senha.into()));
+        self
+    }
+
+    /// Aplica lista de hosts acessados sem proxy, separados por vírgula como em `NO_PROXY`, e.g.
+    /// `localhost,.intranet`.
+    pub fn set_excecoes<S: Into<String>>(mut self, excecoes: S) -> Self {
+        self.excecoes = Some(excecoes.into());
+        self
+    }
+
+    #[inline]
+    fn build(&self) -> result::Result<HttpProxy, ClientError> {
+        let mut proxy = HttpProxy::all(&self.url)?;
+        if let Some((usuario, senha)) = &self.credenciais {
+            proxy = proxy.basic_auth(usuario, senha);
+        }
+        Ok(proxy.no_proxy(self.excecoes.as_deref().and_then(NoProxy::from_string)))
+    }
+}
+
+/// Transporte das requisições SOAP aos webservices.
+///
+/// Permite substituir o [`Client`] padrão por mocks em testes, por outra pilha HTTP ou por
+/// implementações que envolvam o client com _middlewares_, e.g. logs e métricas.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Executa requisição ao servidor informando URL e informações de SOAP como action e XML,
+    /// retornando o corpo da resposta.
+    async fn execute(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult;
+
+    /// Executa requisição que altera dados na SEFAZ, e.g. autorização, que não deve ser repetida
+    /// às cegas. Por padrão, equivale a [`Transport::execute`].
+    async fn execute_nao_idempotente(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
+        self.execute(url, action, xml).await
+    }
+}
+
 /// Client HTTP com suporte a TLS e compressão de dados.
 #[derive(Clone, Debug)]
 pub struct Client {
     inner: HttpClient,
+    tentativas: Tentativas,
 }
 
 impl Client {
-    /// Executa requisição ao servidor informando URL e informações de SOAP como action e XML.
+    /// Executa requisição ao servidor informando URL e informações de SOAP como action e XML,
+    /// repetindo-a conforme a política de tentativas.
     pub async fn execute(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
-        //TODO: tentativas de reconexão
+        self.executar(url, action, xml, true).await
+    }
+
+    /// Executa requisição que altera dados na SEFAZ, e.g. autorização, repetindo-a somente se
+    /// permitido pela política de tentativas ou se a requisição não chegou ao servidor.
+    pub async fn execute_nao_idempotente(
+        &self,
+        url: &str,
+        action: &str,
+        xml: Vec<u8>,
+    ) -> ClientResult {
+        self.executar(url, action, xml, false).await
+    }
+
+    async fn executar(
+        &self,
+        url: &str,
+        action: &str,
+        xml: Vec<u8>,
+        idempotente: bool,
+    ) -> ClientResult {
+        let mut tentativa = 1;
+        loop {
+            match self.enviar(url, action, xml.clone()).await {
+                Err(err)
+                    if tentativa < self.tentativas.maximo
+                        && (err.nao_enviada()
+                            || (self.tentativas.repetivel)(&err)
+                                && (idempotente || self.tentativas.nao_idempotentes)) =>
+                {
+                    time::sleep(self.tentativas.espera(tentativa)).await;
+                    tentativa += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn enviar(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
         let res = self
             .inner
             .post(url)
@@ -40,14 +291,81 @@ impl Client {
             .body(xml)
             .send()
             .await?;
-        Ok(res.bytes().await?.to_vec())
+        let status = res.status();
+        let corpo = res.bytes().await?.to_vec();
+        if let Some((codigo, motivo)) = soap_fault(&corpo) {
+            return Err(ClientError::SoapFault {
+                status: status.as_u16(),
+                codigo,
+                motivo,
+                corpo: String::from_utf8_lossy(&corpo).into_owned(),
+            });
+        }
+        if status.is_client_error() || status.is_server_error() {
+            return Err(ClientError::Http {
+                status: status.as_u16(),
+                corpo: String::from_utf8_lossy(&corpo).into_owned(),
+            });
+        }
+        Ok(corpo)
+    }
+}
+
+#[async_trait]
+impl Transport for Client {
+    async fn execute(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
+        Client::execute(self, url, action, xml).await
+    }
+
+    async fn execute_nao_idempotente(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
+        Client::execute_nao_idempotente(self, url, action, xml).await
     }
 }
 
+/// Extrai código e motivo de SOAP Fault, aceitando também o formato SOAP 1.1 (`faultcode` e
+/// `faultstring`) usado por alguns webservices.
+fn soap_fault(corpo: &[u8]) -> Option<(String, String)> {
+    let xml = str::from_utf8(corpo).ok()?;
+    if !xml.contains("Fault") {
+        return None;
+    }
+    let doc = Document::parse(xml).ok()?;
+    let fault = doc
+        .descendants()
+        .find(|node| node.tag_name().name() == "Fault")?;
+    let descendente = |node: Node, tag: &str| {
+        node.descendants()
+            .find(|n| n.tag_name().name() == tag)
+            .and_then(|n| n.text())
+            .map(|texto| texto.trim().to_string())
+    };
+    let codigo = fault
+        .children()
+        .find(|n| n.tag_name().name() == "Code")
+        .and_then(|code| descendente(code, "Value"))
+        .or_else(|| descendente(fault, "faultcode"))
+        .unwrap_or_default();
+    let motivo = fault
+        .children()
+        .find(|n| n.tag_name().name() == "Reason")
+        .and_then(|reason| descendente(reason, "Text"))
+        .or_else(|| descendente(fault, "faultstring"))
+        .unwrap_or_default();
+    Some((codigo, motivo))
+}
+
 /// Construtor de clients HTTP usando [build pattern](https://en.wikipedia.org/wiki/Builder_pattern).
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ClientBuilder {
-    inner: HttpClientBuilder,
+    pkcs12: Option<Pkcs12Certificate>,
+    connect_timeout: Duration,
+    timeout: Duration,
+    verbose: bool,
+    tentativas: Tentativas,
+    proxy: Option<Proxy>,
+    proxy_ambiente: bool,
+    certificados_raiz: Vec<Vec<u8>>,
+    aceitar_certificados_invalidos: bool,
 }
 
 /// Tipo para tratar retorno do builder de client HTTP.
@@ -57,48 +375,309 @@ impl ClientBuilder {
     /// Cria uma nova instância do builder de client HTTP.
     pub fn new() -> Self {
         Self {
-            inner: HttpClientBuilder::new()
-                .danger_accept_invalid_certs(true)
-                .gzip(true)
-                .user_agent("Rust-Fiscalidade")
-                .timeout(Duration::from_secs(CLIENT_TIMEOUT))
-                .connect_timeout(Duration::from_secs(CLIENT_CONNECT_TIMEOUT)),
+            pkcs12: None,
+            connect_timeout: Duration::from_secs(CLIENT_CONNECT_TIMEOUT),
+            timeout: Duration::from_secs(CLIENT_TIMEOUT),
+            verbose: false,
+            tentativas: Tentativas::default(),
+            proxy: None,
+            proxy_ambiente: true,
+            certificados_raiz: Vec::new(),
+            aceitar_certificados_invalidos: false,
         }
     }
 
     /// Aplica certificado PKCS12 ao client HTTP criado.
-    pub fn set_pkcs12(self, pkcs12: Pkcs12Certificate) -> Self {
-        self.with_inner(|inner| inner.identity(pkcs12.into_inner()))
+    pub fn set_pkcs12(mut self, pkcs12: Pkcs12Certificate) -> Self {
+        self.pkcs12 = Some(pkcs12);
+        self
     }
 
     /// Aplica tempo máximo de timeout para conexão do client HTTP criado.
-    pub fn set_connect_timeout(self, timeout: Duration) -> Self {
-        self.with_inner(|inner| inner.connect_timeout(timeout))
+    pub fn set_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
     }
 
     /// Aplica tempo máximo de timeout para transmissão de dados do client HTTP criado.
-    pub fn set_timeout(self, timeout: Duration) -> Self {
-        self.with_inner(|inner| inner.timeout(timeout))
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
     }
 
     /// Torna o client HTTP criado mais verboso, i.e. emite mais informações de log.
-    pub fn set_verbose(self, verbose: bool) -> Self {
-        self.with_inner(move |inner| inner.connection_verbose(verbose))
+    pub fn set_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Aplica política de novas tentativas de comunicação ao client HTTP criado.
+    pub fn set_tentativas(mut self, tentativas: Tentativas) -> Self {
+        self.tentativas = tentativas;
+        self
+    }
+
+    /// Aplica proxy ao client HTTP criado, com prioridade sobre as variáveis de ambiente.
+    pub fn set_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Define se o client HTTP criado usa os proxies das variáveis de ambiente `HTTP_PROXY`,
+    /// `HTTPS_PROXY`, `ALL_PROXY` e `NO_PROXY`, habilitado por padrão.
+    pub fn set_proxy_ambiente(mut self, proxy_ambiente: bool) -> Self {
+        self.proxy_ambiente = proxy_ambiente;
+        self
+    }
+
+    /// Adiciona certificado de autoridade certificadora confiável, em PEM (podendo conter vários
+    /// certificados) ou DER, além dos certificados do sistema.
+    ///
+    /// Os webservices da SEFAZ usam certificados emitidos pela ICP-Brasil, que não fazem parte dos
+    /// repositórios de certificados dos sistemas operacionais. A cadeia da AC Raiz e das ACs
+    /// intermediárias, publicada pelo ITI, é embutida pela _feature_ `embed_icp_brasil`; sem ela,
+    /// ou para ACs mais recentes que a cadeia embutida, adicione os certificados aqui.
+    pub fn add_root_certificate(mut self, certificado: &[u8]) -> Self {
+        self.certificados_raiz.push(certificado.to_vec());
+        self
+    }
+
+    /// Desabilita a verificação do certificado dos servidores, aceitando inclusive certificados
+    /// inválidos ou expirados.
+    ///
+    /// **Perigoso**: permite que terceiros interceptem a comunicação com os webservices. Use
+    /// somente se não for possível adicionar a cadeia da ICP-Brasil.
+    pub fn set_aceitar_certificados_invalidos(mut self, aceitar: bool) -> Self {
+        self.aceitar_certificados_invalidos = aceitar;
+        self
     }
 
     /// Constrói novo client HTTP pré-configurado.
     pub fn build(self) -> ClientBuilderResult {
+        let mut inner = HttpClientBuilder::new()
+            .gzip(true)
+            .user_agent("Rust-Fiscalidade")
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .connection_verbose(self.verbose);
+        inner = self.tls(inner)?;
+        if !self.proxy_ambiente {
+            inner = inner.no_proxy();
+        }
+        if let Some(proxy) = &self.proxy {
+            inner = inner.proxy(proxy.build()?);
+        }
         Ok(Client {
-            inner: self.inner.build()?,
+            inner: inner.build()?,
+            tentativas: self.tentativas,
         })
     }
 
-    #[inline]
-    fn with_inner<F>(mut self, func: F) -> Self
-    where
-        F: FnOnce(HttpClientBuilder) -> HttpClientBuilder,
-    {
-        self.inner = func(self.inner);
-        self
+    #[cfg(not(feature = "rustls"))]
+    fn tls(&self, mut inner: HttpClientBuilder) -> result::Result<HttpClientBuilder, ClientError> {
+        inner = inner.danger_accept_invalid_certs(self.aceitar_certificados_invalidos);
+        if let Some(pkcs12) = &self.pkcs12 {
+            inner = inner.identity(pkcs12.clone().into_inner());
+        }
+        let certificados_raiz = self.certificados_raiz.iter().map(Vec::as_slice);
+        for certificado in ICP_BRASIL.iter().copied().chain(certificados_raiz) {
+            if certificado.starts_with(b"-----BEGIN") {
+                for certificado in Certificate::from_pem_bundle(certificado)? {
+                    inner = inner.add_root_certificate(certificado);
+                }
+            } else {
+                inner = inner.add_root_certificate(Certificate::from_der(certificado)?);
+            }
+        }
+        Ok(inner)
+    }
+
+    #[cfg(feature = "rustls")]
+    fn tls(&self, inner: HttpClientBuilder) -> result::Result<HttpClientBuilder, ClientError> {
+        Ok(inner.use_preconfigured_tls(crate::tls::configurar(
+            self.pkcs12.clone(),
+            &self.certificados_raiz,
+            self.aceitar_certificados_invalidos,
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soap_fault_12() {
+        let corpo = concat!(
+            "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\"><soap:Body>",
+            "<soap:Fault><soap:Code><soap:Value>soap:Receiver</soap:Value>",
+            "<soap:Subcode><soap:Value>a:Erro</soap:Value></soap:Subcode></soap:Code>",
+            "<soap:Reason><soap:Text xml:lang=\"pt-BR\"> Servidor indisponivel </soap:Text>",
+            "</soap:Reason></soap:Fault>",
+            "</soap:Body></soap:Envelope>"
+        );
+        assert_eq!(
+            soap_fault(corpo.as_bytes()),
+            Some((
+                "soap:Receiver".to_string(),
+                "Servidor indisponivel".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn soap_fault_11() {
+        let corpo = concat!(
+            "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\"><s:Body><s:Fault>",
+            "<faultcode>s:Client</faultcode><faultstring>Action invalida</faultstring>",
+            "</s:Fault></s:Body></s:Envelope>"
+        );
+        assert_eq!(
+            soap_fault(corpo.as_bytes()),
+            Some(("s:Client".to_string(), "Action invalida".to_string()))
+        );
+    }
+
+    #[test]
+    fn sem_soap_fault() {
+        let corpo = concat!(
+            "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\"><soap:Body>",
+            "<retConsStatServ><xMotivo>Fault no texto</xMotivo></retConsStatServ>",
+            "</soap:Body></soap:Envelope>"
+        );
+        assert_eq!(soap_fault(corpo.as_bytes()), None);
+        assert_eq!(soap_fault(b"<html>Fault</html"), None);
+        assert_eq!(soap_fault(&[0xff, 0xfe]), None);
+        assert_eq!(
+            soap_fault(b"<Fault/>"),
+            Some((String::new(), String::new()))
+        );
+    }
+
+    #[test]
+    fn erro_http() {
+        for (status, repetivel) in [
+            (400, false),
+            (500, false),
+            (502, true),
+            (503, true),
+            (504, true),
+        ] {
+            let err = ClientError::Http {
+                status,
+                corpo: String::new(),
+            };
+            assert_eq!(err.repetivel(), repetivel);
+            assert_eq!(err.to_string(), format!("Erro HTTP {}", status));
+        }
+        let err = ClientError::SoapFault {
+            status: 500,
+            codigo: "soap:Receiver".to_string(),
+            motivo: "Erro interno".to_string(),
+            corpo: String::new(),
+        };
+        assert!(!err.repetivel());
+        assert_eq!(err.to_string(), "SOAP Fault soap:Receiver: Erro interno");
+        assert!(!ClientError::Externo("falha".into()).repetivel());
+    }
+
+    #[test]
+    fn tentativas_maximo() {
+        assert_eq!(Tentativas::new(0).maximo, 1);
+        assert_eq!(Tentativas::new(3).maximo, 3);
+        assert_eq!(Tentativas::default().maximo, 1);
+        assert!(!Tentativas::default().nao_idempotentes);
+        assert!(
+            Tentativas::new(1)
+                .set_nao_idempotentes(true)
+                .nao_idempotentes
+        );
+    }
+
+    #[test]
+    fn tentativas_espera() {
+        let tentativas = Tentativas::new(10)
+            .set_espera_inicial(Duration::from_millis(100))
+            .set_espera_maxima(Duration::from_millis(1_000));
+        for (tentativa, espera) in [
+            (1, 100),
+            (2, 200),
+            (3, 400),
+            (4, 800),
+            (5, 1_000),
+            (30, 1_000),
+        ] {
+            let espera = Duration::from_millis(espera);
+            for _ in 0..20 {
+                let atual = tentativas.espera(tentativa);
+                assert!(atual >= espera / 2 && atual <= espera, "{:?}", atual);
+            }
+        }
+        let tentativas = Tentativas::new(u32::MAX).set_espera_maxima(Duration::MAX);
+        assert!(tentativas.espera(u32::MAX) <= Duration::MAX);
+    }
+
+    #[test]
+    fn tentativas_repetivel() {
+        let err = ClientError::Http {
+            status: 500,
+            corpo: String::new(),
+        };
+        assert!(!(Tentativas::default().repetivel)(&err));
+        let tentativas =
+            Tentativas::default().set_repetivel(|err| matches!(err, ClientError::Http { .. }));
+        assert!((tentativas.repetivel)(&err));
+    }
+
+    #[test]
+    fn proxy() {
+        let proxy = Proxy::new("http://proxy:3128")
+            .set_credenciais("usuario", "senha")
+            .set_excecoes("localhost,.intranet");
+        assert_eq!(
+            proxy.credenciais,
+            Some(("usuario".to_string(), "senha".to_string()))
+        );
+        assert_eq!(proxy.excecoes.as_deref(), Some("localhost,.intranet"));
+        assert!(proxy.build().is_ok());
+        assert!(Proxy::new("socks5://proxy:1080").build().is_ok());
+        assert!(Proxy::new("https://proxy").set_excecoes("").build().is_ok());
+    }
+
+    #[test]
+    fn proxy_invalido() {
+        assert!(matches!(
+            Proxy::new("não é url").build(),
+            Err(ClientError::HttpClient(_))
+        ));
+        assert!(matches!(
+            ClientBuilder::new()
+                .set_proxy(Proxy::new("http://[::1"))
+                .build(),
+            Err(ClientError::HttpClient(_))
+        ));
+    }
+
+    #[cfg(feature = "embed_icp_brasil")]
+    #[test]
+    fn icp_brasil() {
+        use x509_parser::{certificate::X509Certificate, pem::Pem, prelude::FromDer};
+
+        assert!(!ICP_BRASIL.is_empty());
+        for certificado in ICP_BRASIL {
+            if certificado.starts_with(b"-----BEGIN") {
+                for pem in Pem::iter_from_buffer(certificado) {
+                    assert!(pem.unwrap().parse_x509().is_ok());
+                }
+            } else {
+                assert!(X509Certificate::from_der(certificado).is_ok());
+            }
+        }
+    }
+
+    #[cfg(not(feature = "embed_icp_brasil"))]
+    #[test]
+    fn icp_brasil() {
+        assert!(ICP_BRASIL.is_empty());
     }
 }
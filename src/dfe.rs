@@ -1,33 +1,130 @@
-use std::{fmt, result};
+use std::{
+    fmt, result, str,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
+use roxmltree::Document;
 use thiserror::Error;
+use tokio::time;
 
 use crate::{
-    client::{ClientBuilder, ClientError},
+    assinatura::{Assinador, AssinaturaError},
+    client::{Client, ClientBuilder, ClientError, Proxy, Tentativas, Transport},
+    retorno::{
+        self, ProtNFe, RetConsReciNFe, RetDistDFeInt, RetEnvEvento, RetEnviNFe, RetInutNFe,
+        Retorno, RetornoError, RetornoResult,
+    },
     soap12,
-    tipos::{Ambiente, Documento, Modelo, Servico, Uf},
+    status::{ClassificacaoStatus, CodigoStatus},
+    tipos::{
+        Ambiente, Documento, Manifestacao, Modelo, Processamento, Servico, TipoDocumento,
+        TipoEvento, Uf,
+    },
     util,
     webservices::{WebServices, WebServicesBuilder, WebServicesBuilderError},
     Pkcs12Certificate,
 };
 
+/// Quantidade máxima de notas permitidas em um lote.
+pub const MAX_NOTAS_LOTE: usize = 50;
+
+/// Versão da aplicação informada à SEFAZ em eventos.
+pub const VER_APLIC: &str = concat!("fiscalidade-", env!("CARGO_PKG_VERSION"));
+
+/// Código do órgão de recepção de eventos do Ambiente Nacional.
+const C_ORGAO_AMBIENTE_NACIONAL: u8 = 91;
+
+/// Tempo padrão de espera, em segundos, entre consultas de recibo quando a SEFAZ não informa `tMed`.
+pub const ESPERA_CONSULTA_RECIBO: u64 = 1;
+
+/// Tempo máximo de espera, em segundos, entre consultas de recibo.
+pub const MAX_ESPERA_CONSULTA_RECIBO: u64 = 30;
+
 #[derive(Error, Debug)]
 pub enum DfeError {
     #[error(transparent)]
     WebServices(#[from] WebServicesBuilderError),
     #[error(transparent)]
     Client(#[from] ClientError),
+    #[error(transparent)]
+    Retorno(#[from] RetornoError),
+    #[error(transparent)]
+    Assinatura(#[from] AssinaturaError),
+    #[error("Assinador de XML não informado")]
+    AssinadorNaoInformado,
     #[error("Operação inexistente para este serviço")]
     OperacaoInexistente,
     #[error("Chave de NF-e inválida: {0}")]
     ChaveInvalida(String),
     #[error("Código de recibo inválido: {0}")]
     ReciboInvalido(String),
+    #[error("Identificador de lote inválido: {0}")]
+    LoteInvalido(String),
+    #[error("Quantidade de notas inválida para o lote: {0}")]
+    QuantidadeNotasInvalida(usize),
+    #[error("Rejeição {c_stat}: {x_motivo}")]
+    Rejeicao {
+        c_stat: u16,
+        x_motivo: String,
+        xml: String,
+    },
+    #[error("Lote ainda em processamento, recibo: {0}")]
+    LoteEmProcessamento(String),
+    #[error("Número de protocolo inválido: {0}")]
+    ProtocoloInvalido(String),
+    #[error("Justificativa deve conter entre 15 e 255 caracteres")]
+    JustificativaInvalida,
+    #[error("Correção deve conter entre 15 e 1000 caracteres")]
+    CorrecaoInvalida,
+    #[error("Número sequencial de evento inválido: {0}")]
+    SequenciaInvalida(u8),
+    #[error("Documento inválido para esta operação: {0}")]
+    DocumentoInvalido(String),
+    #[error("Modelo de NF não suportado para esta operação: {0}")]
+    ModeloNaoSuportado(Modelo),
+    #[error("Tipo de emissão inválido para esta operação: {0}")]
+    TipoEmissaoInvalido(String),
+    #[error("CNPJ inválido: {0}")]
+    CnpjInvalido(String),
+    #[error("Série inválida: {0}")]
+    SerieInvalida(u16),
+    #[error("Faixa de numeração inválida: {n_ini} a {n_fin}")]
+    NumeracaoInvalida { n_ini: u32, n_fin: u32 },
+    #[error("Prazo de cancelamento excedido ou data de emissão inválida: {0}")]
+    PrazoCancelamentoExcedido(String),
+}
+
+impl DfeError {
+    /// Classificação do código de status quando o erro é uma rejeição da SEFAZ, útil para decidir
+    /// se o pedido pode ser repetido.
+    pub fn classificacao(&self) -> Option<ClassificacaoStatus> {
+        match *self {
+            DfeError::Rejeicao { c_stat, .. } => Some(CodigoStatus::classificar(c_stat)),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct Dfe {
     webservices_builder: WebServicesBuilder,
     client_builder: ClientBuilder,
+    /// Client HTTP construído na primeira requisição e compartilhado entre os clones, reiniciado
+    /// quando a configuração do client muda.
+    client: Arc<OnceLock<Arc<Client>>>,
+    transport: Option<Arc<dyn Transport>>,
+    assinador: Option<Arc<dyn Assinador>>,
+    assinador_pkcs12: bool,
+}
+
+struct Evento<'a> {
+    c_orgao: u8,
+    autor: Documento<'a>,
+    chave: &'a str,
+    tipo: TipoEvento,
+    n_seq: u8,
+    det: String,
 }
 
 #[derive(Debug)]
@@ -35,6 +132,21 @@ pub struct Xml(pub Vec<u8>);
 
 pub type DfeResult = result::Result<Xml, DfeError>;
 
+impl Xml {
+    pub fn retorno<T: Retorno>(&self) -> RetornoResult<T> {
+        T::from_xml(str::from_utf8(&self.0)?)
+    }
+
+    #[inline]
+    fn rejeicao(&self, c_stat: u16, x_motivo: String) -> DfeError {
+        DfeError::Rejeicao {
+            c_stat,
+            x_motivo,
+            xml: self.to_string(),
+        }
+    }
+}
+
 impl fmt::Display for Xml {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", String::from_utf8_lossy(&self.0))
@@ -46,6 +158,10 @@ impl Dfe {
         Self {
             webservices_builder: WebServicesBuilder::new(),
             client_builder: ClientBuilder::new(),
+            client: Arc::default(),
+            transport: None,
+            assinador: None,
+            assinador_pkcs12: false,
         }
     }
 
@@ -53,10 +169,48 @@ impl Dfe {
         self.with_ws_builder(|ws_builder| ws_builder.set_toml(webservices))
     }
 
-    pub fn set_pkcs12(self, pkcs12: Pkcs12Certificate) -> Self {
+    pub fn set_pkcs12(mut self, pkcs12: Pkcs12Certificate) -> Self {
+        // Certificados A1 também assinam os XMLs, a menos que outro assinador tenha sido informado.
+        if self.assinador.is_none() || self.assinador_pkcs12 {
+            self.assinador = Some(Arc::new(pkcs12.clone()));
+            self.assinador_pkcs12 = true;
+        }
         self.with_cli_builder(|cli_builder| cli_builder.set_pkcs12(pkcs12))
     }
 
+    pub fn set_proxy(self, proxy: Proxy) -> Self {
+        self.with_cli_builder(|cli_builder| cli_builder.set_proxy(proxy))
+    }
+
+    pub fn set_proxy_ambiente(self, proxy_ambiente: bool) -> Self {
+        self.with_cli_builder(|cli_builder| cli_builder.set_proxy_ambiente(proxy_ambiente))
+    }
+
+    pub fn add_root_certificate(self, certificado: &[u8]) -> Self {
+        self.with_cli_builder(|cli_builder| cli_builder.add_root_certificate(certificado))
+    }
+
+    pub fn set_aceitar_certificados_invalidos(self, aceitar: bool) -> Self {
+        self.with_cli_builder(|cli_builder| cli_builder.set_aceitar_certificados_invalidos(aceitar))
+    }
+
+    pub fn set_tentativas(self, tentativas: Tentativas) -> Self {
+        self.with_cli_builder(|cli_builder| cli_builder.set_tentativas(tentativas))
+    }
+
+    /// Substitui o client HTTP padrão pelo transporte informado, que passa a ignorar as
+    /// configurações de certificado, proxy, TLS e tentativas.
+    pub fn set_transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    pub fn set_assinador<A: Assinador + 'static>(mut self, assinador: A) -> Self {
+        self.assinador = Some(Arc::new(assinador));
+        self.assinador_pkcs12 = false;
+        self
+    }
+
     pub async fn status_servico(self, modelo: Modelo, uf: Uf, ambiente: Ambiente) -> DfeResult {
         self.send(
             modelo,
@@ -144,6 +298,539 @@ impl Dfe {
         .await
     }
 
+    /// Transmite lote de notas, retornando o `retEnviNFe` com o recibo, no processamento
+    /// assíncrono, ou com o protocolo da nota, no síncrono. Rejeições do lote ou da nota são
+    /// retornadas como [`DfeError::Rejeicao`].
+    pub async fn autorizar(
+        self,
+        modelo: Modelo,
+        uf: Uf,
+        ambiente: Ambiente,
+        id_lote: &str,
+        processamento: Processamento,
+        nfes: &[&str],
+    ) -> DfeResult {
+        validar_lote(id_lote, processamento, nfes)?;
+        let xml = self
+            .send(
+                modelo,
+                uf,
+                ambiente,
+                Servico::Envio,
+                |_, _, versao, operacao| {
+                    soap12::format_envi_nfe(
+                        versao,
+                        operacao,
+                        id_lote,
+                        processamento.ind_sinc(),
+                        nfes,
+                    )
+                },
+            )
+            .await?;
+        let ret: RetEnviNFe = xml.retorno()?;
+        // 103 - Lote recebido com sucesso, 104 - Lote processado
+        if !matches!(ret.c_stat, 103 | 104) {
+            return Err(xml.rejeicao(ret.c_stat, ret.x_motivo));
+        }
+        // Denegações são registradas pela SEFAZ e retornadas normalmente, como as autorizações.
+        if let Some(prot) = ret.prot_nfe {
+            if matches!(
+                CodigoStatus::classificar(prot.c_stat),
+                ClassificacaoStatus::Rejeicao | ClassificacaoStatus::Transitoria
+            ) {
+                return Err(xml.rejeicao(prot.c_stat, prot.x_motivo));
+            }
+        }
+        Ok(xml)
+    }
+
+    pub async fn autorizar_lote(
+        self,
+        modelo: Modelo,
+        uf: Uf,
+        ambiente: Ambiente,
+        id_lote: &str,
+        nfes: &[&str],
+        tentativas: u32,
+    ) -> result::Result<Vec<ProtNFe>, DfeError> {
+        validar_lote(id_lote, Processamento::Assincrono, nfes)?;
+        let cli = self.transport()?;
+        let ws_builder = self.webservices_builder;
+        let xml = Self::execute(
+            cli.as_ref(),
+            ws_builder.clone(),
+            modelo,
+            uf,
+            ambiente,
+            Servico::Envio,
+            |_, _, versao, operacao| {
+                soap12::format_envi_nfe(
+                    versao,
+                    operacao,
+                    id_lote,
+                    Processamento::Assincrono.ind_sinc(),
+                    nfes,
+                )
+            },
+        )
+        .await?;
+        let ret: RetEnviNFe = xml.retorno()?;
+        // 103 - Lote recebido com sucesso
+        let recibo = match ret.n_rec {
+            Some(recibo) if ret.c_stat == 103 => recibo,
+            _ => return Err(xml.rejeicao(ret.c_stat, ret.x_motivo)),
+        };
+        let espera = Duration::from_secs(
+            ret.t_med
+                .map_or(ESPERA_CONSULTA_RECIBO, u64::from)
+                .clamp(ESPERA_CONSULTA_RECIBO, MAX_ESPERA_CONSULTA_RECIBO),
+        );
+        for _ in 0..tentativas {
+            time::sleep(espera).await;
+            let xml = Self::execute(
+                cli.as_ref(),
+                ws_builder.clone(),
+                modelo,
+                uf,
+                ambiente,
+                Servico::ConsultaAutorizacao,
+                |_, tp_amb, versao, operacao| {
+                    soap12::format_cons_reci(tp_amb, versao, operacao, &recibo)
+                },
+            )
+            .await?;
+            let ret: RetConsReciNFe = xml.retorno()?;
+            match ret.c_stat {
+                // 104 - Lote processado
+                104 => return Ok(ret.prot_nfe),
+                // 105 - Lote em processamento
+                105 => continue,
+                c_stat => return Err(xml.rejeicao(c_stat, ret.x_motivo)),
+            }
+        }
+        Err(DfeError::LoteEmProcessamento(recibo))
+    }
+
+    pub async fn cancelar(
+        self,
+        ambiente: Ambiente,
+        chave: &str,
+        protocolo: &str,
+        justificativa: &str,
+        dh_emi: Option<&str>,
+    ) -> result::Result<RetEnvEvento, DfeError> {
+        let (modelo, uf) = dados_chave(chave)?;
+        if !util::validar_protocolo(protocolo) {
+            return Err(DfeError::ProtocoloInvalido(protocolo.to_string()));
+        }
+        if !util::validar_justificativa(justificativa) {
+            return Err(DfeError::JustificativaInvalida);
+        }
+        if let Some(dh_emi) = dh_emi {
+            if !util::validar_prazo_cancelamento(dh_emi) {
+                return Err(DfeError::PrazoCancelamentoExcedido(dh_emi.to_string()));
+            }
+        }
+        let tipo = TipoEvento::Cancelamento;
+        self.enviar_evento(
+            modelo,
+            uf,
+            ambiente,
+            Evento {
+                c_orgao: uf.cuf(),
+                autor: util::extrair_emitente(chave),
+                chave,
+                tipo,
+                n_seq: 1,
+                det: soap12::format_det_cancelamento(tipo.descricao(), protocolo, justificativa),
+            },
+        )
+        .await
+    }
+
+    pub async fn cancelar_por_substituicao(
+        self,
+        ambiente: Ambiente,
+        chave: &str,
+        protocolo: &str,
+        chave_substituta: &str,
+        justificativa: &str,
+    ) -> result::Result<RetEnvEvento, DfeError> {
+        let (modelo, uf) = dados_chave(chave)?;
+        // Cancelamento por substituição existe apenas para NFC-e.
+        if modelo != Modelo::Nfce {
+            return Err(DfeError::ModeloNaoSuportado(modelo));
+        }
+        if dados_chave(chave_substituta).ok() != Some((modelo, uf)) || chave_substituta == chave {
+            return Err(DfeError::ChaveInvalida(chave_substituta.to_string()));
+        }
+        if !util::validar_protocolo(protocolo) {
+            return Err(DfeError::ProtocoloInvalido(protocolo.to_string()));
+        }
+        if !util::validar_justificativa(justificativa) {
+            return Err(DfeError::JustificativaInvalida);
+        }
+        let tipo = TipoEvento::CancelamentoSubstituicao;
+        self.enviar_evento(
+            modelo,
+            uf,
+            ambiente,
+            Evento {
+                c_orgao: uf.cuf(),
+                autor: util::extrair_emitente(chave),
+                chave,
+                tipo,
+                n_seq: 1,
+                det: soap12::format_det_cancelamento_substituicao(
+                    tipo.descricao(),
+                    uf.cuf(),
+                    VER_APLIC,
+                    protocolo,
+                    justificativa,
+                    chave_substituta,
+                ),
+            },
+        )
+        .await
+    }
+
+    pub async fn carta_correcao(
+        self,
+        ambiente: Ambiente,
+        chave: &str,
+        sequencia: u8,
+        correcao: &str,
+    ) -> result::Result<RetEnvEvento, DfeError> {
+        let (modelo, uf) = dados_chave(chave)?;
+        // Carta de correção existe apenas para NF-e.
+        if modelo != Modelo::Nfe {
+            return Err(DfeError::ModeloNaoSuportado(modelo));
+        }
+        if !util::validar_sequencia_cce(sequencia) {
+            return Err(DfeError::SequenciaInvalida(sequencia));
+        }
+        if !util::validar_correcao(correcao) {
+            return Err(DfeError::CorrecaoInvalida);
+        }
+        let tipo = TipoEvento::CartaCorrecao;
+        self.enviar_evento(
+            modelo,
+            uf,
+            ambiente,
+            Evento {
+                c_orgao: uf.cuf(),
+                autor: util::extrair_emitente(chave),
+                chave,
+                tipo,
+                n_seq: sequencia,
+                det: soap12::format_det_carta_correcao(tipo.descricao(), correcao),
+            },
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn inutilizar(
+        self,
+        modelo: Modelo,
+        uf: Uf,
+        ambiente: Ambiente,
+        ano: u16,
+        cnpj: &str,
+        serie: u16,
+        n_ini: u32,
+        n_fin: u32,
+        justificativa: &str,
+    ) -> result::Result<RetInutNFe, DfeError> {
+        if !util::validar_cnpj(cnpj) {
+            return Err(DfeError::CnpjInvalido(cnpj.to_string()));
+        }
+        if serie > 999 {
+            return Err(DfeError::SerieInvalida(serie));
+        }
+        if !util::validar_numeracao(n_ini, n_fin) {
+            return Err(DfeError::NumeracaoInvalida { n_ini, n_fin });
+        }
+        if !util::validar_justificativa(justificativa) {
+            return Err(DfeError::JustificativaInvalida);
+        }
+        let assinador = self
+            .assinador
+            .clone()
+            .ok_or(DfeError::AssinadorNaoInformado)?;
+        // Aceita ano com quatro dígitos, mas a SEFAZ espera apenas os dois últimos.
+        let ano = ano % 100;
+        let id = soap12::format_id_inutilizacao(
+            uf.cuf(),
+            ano,
+            cnpj,
+            modelo.codigo(),
+            serie,
+            n_ini,
+            n_fin,
+        );
+        let xml = soap12::format_inut_nfe(
+            Servico::Inutilizacao.versao_url().as_str(),
+            &id,
+            ambiente.tp_amb(),
+            uf.cuf(),
+            ano,
+            cnpj,
+            modelo.codigo(),
+            serie,
+            n_ini,
+            n_fin,
+            justificativa,
+        );
+        let xml = assinador.assinar(&xml, &id)?;
+        let retorno = self
+            .send(
+                modelo,
+                uf,
+                ambiente,
+                Servico::Inutilizacao,
+                |_, _, _, operacao| soap12::format_dados_msg(&xml, operacao),
+            )
+            .await?;
+        let ret: RetInutNFe = retorno.retorno()?;
+        // 102 - Inutilização de número homologado
+        if ret.c_stat != 102 {
+            return Err(retorno.rejeicao(ret.c_stat, ret.x_motivo));
+        }
+        Ok(ret)
+    }
+
+    pub async fn manifestar(
+        self,
+        ambiente: Ambiente,
+        chave: &str,
+        autor: Documento<'_>,
+        manifestacao: Manifestacao,
+        justificativa: Option<&str>,
+    ) -> result::Result<RetEnvEvento, DfeError> {
+        let (modelo, uf) = dados_chave(chave)?;
+        // Manifestação do destinatário existe apenas para NF-e.
+        if modelo != Modelo::Nfe {
+            return Err(DfeError::ModeloNaoSuportado(modelo));
+        }
+        if autor.tipo() == TipoDocumento::Ie {
+            return Err(DfeError::DocumentoInvalido(autor.to_string()));
+        }
+        // Justificativa é obrigatória apenas para "Operação não realizada".
+        let justificativa = match manifestacao {
+            Manifestacao::OperacaoNaoRealizada => match justificativa {
+                Some(justificativa) if util::validar_justificativa(justificativa) => {
+                    Some(justificativa)
+                }
+                _ => return Err(DfeError::JustificativaInvalida),
+            },
+            _ => None,
+        };
+        let tipo = manifestacao.tipo_evento();
+        self.enviar_evento(
+            modelo,
+            uf,
+            ambiente,
+            Evento {
+                c_orgao: C_ORGAO_AMBIENTE_NACIONAL,
+                autor,
+                chave,
+                tipo,
+                n_seq: 1,
+                det: soap12::format_det_manifestacao(tipo.descricao(), justificativa),
+            },
+        )
+        .await
+    }
+
+    pub async fn enviar_epec(
+        self,
+        ambiente: Ambiente,
+        nfe: &str,
+    ) -> result::Result<RetEnvEvento, DfeError> {
+        let nfe = DadosNfe::from_xml(nfe)?;
+        validar_epec(&nfe)?;
+        let uf = util::extrair_uf(&nfe.chave)
+            .ok_or_else(|| DfeError::ChaveInvalida(nfe.chave.clone()))?;
+        let tipo = TipoEvento::Epec;
+        let det = soap12::format_det_epec(
+            tipo.descricao(),
+            uf.cuf(),
+            VER_APLIC,
+            &nfe.dh_emi,
+            &nfe.tp_nf,
+            &nfe.emit_ie,
+            &nfe.dest_uf,
+            &nfe.dest,
+            nfe.dest_tag,
+            nfe.dest_ie.as_deref(),
+            &nfe.v_nf,
+            &nfe.v_icms,
+            &nfe.v_st,
+        );
+        self.enviar_evento(
+            Modelo::Nfe,
+            uf,
+            ambiente,
+            Evento {
+                c_orgao: C_ORGAO_AMBIENTE_NACIONAL,
+                autor: if nfe.emit_tag == "CPF" {
+                    Documento::from_cpf(&nfe.emit)
+                } else {
+                    Documento::from_cnpj(&nfe.emit)
+                },
+                chave: &nfe.chave,
+                tipo,
+                n_seq: 1,
+                det,
+            },
+        )
+        .await
+    }
+
+    /// Transmite para autorização nota emitida em contingência EPEC, após o retorno da SEFAZ.
+    pub async fn transmitir_epec(self, ambiente: Ambiente, id_lote: &str, nfe: &str) -> DfeResult {
+        let dados = DadosNfe::from_xml(nfe)?;
+        validar_epec(&dados)?;
+        let uf = util::extrair_uf(&dados.chave)
+            .ok_or_else(|| DfeError::ChaveInvalida(dados.chave.clone()))?;
+        self.autorizar(
+            Modelo::Nfe,
+            uf,
+            ambiente,
+            id_lote,
+            Processamento::Sincrono,
+            &[nfe],
+        )
+        .await
+    }
+
+    async fn enviar_evento(
+        self,
+        modelo: Modelo,
+        uf: Uf,
+        ambiente: Ambiente,
+        evento: Evento<'_>,
+    ) -> result::Result<RetEnvEvento, DfeError> {
+        let assinador = self
+            .assinador
+            .clone()
+            .ok_or(DfeError::AssinadorNaoInformado)?;
+        let id = soap12::format_id_evento(evento.tipo.codigo(), evento.chave, evento.n_seq);
+        let xml = soap12::format_evento(
+            &id,
+            evento.c_orgao,
+            ambiente.tp_amb(),
+            evento.autor.as_str(),
+            evento.autor.tipo().as_str(),
+            evento.chave,
+            &util::data_hora_atual(),
+            evento.tipo.codigo(),
+            evento.n_seq,
+            &evento.det,
+        );
+        let xml = assinador.assinar(&xml, &id)?;
+        let id_lote = util::gerar_id_lote();
+        let retorno = self
+            .send(
+                modelo,
+                uf,
+                ambiente,
+                evento.tipo.servico(),
+                |_, _, _, operacao| soap12::format_env_evento(operacao, &id_lote, &[xml]),
+            )
+            .await?;
+        let ret: RetEnvEvento = retorno.retorno()?;
+        // 128 - Lote de evento processado
+        if ret.c_stat != 128 {
+            return Err(retorno.rejeicao(ret.c_stat, ret.x_motivo));
+        }
+        // 135 - Evento registrado e vinculado a NF-e, 136 - Evento registrado, mas não vinculado a
+        // NF-e, 155 - Cancelamento homologado fora de prazo
+        if let Some(evento) = ret
+            .ret_evento
+            .iter()
+            .find(|evento| !matches!(evento.c_stat, 135 | 136 | 155))
+        {
+            return Err(retorno.rejeicao(evento.c_stat, evento.x_motivo.clone()));
+        }
+        Ok(ret)
+    }
+
+    pub async fn distribuicao_por_ultimo_nsu(
+        self,
+        uf: Uf,
+        ambiente: Ambiente,
+        documento: Documento<'_>,
+        ult_nsu: u64,
+    ) -> result::Result<RetDistDFeInt, DfeError> {
+        self.distribuir(uf, ambiente, documento, &soap12::format_dist_nsu(ult_nsu))
+            .await
+    }
+
+    pub async fn distribuicao_por_nsu(
+        self,
+        uf: Uf,
+        ambiente: Ambiente,
+        documento: Documento<'_>,
+        nsu: u64,
+    ) -> result::Result<RetDistDFeInt, DfeError> {
+        self.distribuir(uf, ambiente, documento, &soap12::format_cons_nsu(nsu))
+            .await
+    }
+
+    pub async fn distribuicao_por_chave(
+        self,
+        uf: Uf,
+        ambiente: Ambiente,
+        documento: Documento<'_>,
+        chave: &str,
+    ) -> result::Result<RetDistDFeInt, DfeError> {
+        if !util::validar_chave(chave) {
+            return Err(DfeError::ChaveInvalida(chave.to_string()));
+        }
+        self.distribuir(uf, ambiente, documento, &soap12::format_cons_ch_nfe(chave))
+            .await
+    }
+
+    async fn distribuir(
+        self,
+        uf: Uf,
+        ambiente: Ambiente,
+        documento: Documento<'_>,
+        consulta: &str,
+    ) -> result::Result<RetDistDFeInt, DfeError> {
+        if documento.tipo() == TipoDocumento::Ie {
+            return Err(DfeError::DocumentoInvalido(documento.to_string()));
+        }
+        let retorno = self
+            .send(
+                Modelo::Nfe,
+                uf,
+                ambiente,
+                Servico::DistribuicaoDfe,
+                |cuf, tp_amb, versao, operacao| {
+                    soap12::format_dist_dfe_int(
+                        cuf,
+                        tp_amb,
+                        versao,
+                        operacao,
+                        documento.as_str(),
+                        documento.tipo().as_str(),
+                        consulta,
+                    )
+                },
+            )
+            .await?;
+        let ret: RetDistDFeInt = retorno.retorno()?;
+        // 137 - Nenhum documento localizado, 138 - Documento localizado
+        if !matches!(ret.c_stat, 137 | 138) {
+            return Err(retorno.rejeicao(ret.c_stat, ret.x_motivo));
+        }
+        Ok(ret)
+    }
+
     #[inline]
     async fn send<F>(
         self,
@@ -153,21 +840,41 @@ impl Dfe {
         servico: Servico,
         envelope_fn: F,
     ) -> DfeResult
+    where
+        F: FnOnce(u8, u8, &str, &str) -> String,
+    {
+        let cli = self.transport()?;
+        Self::execute(
+            cli.as_ref(),
+            self.webservices_builder,
+            modelo,
+            uf,
+            ambiente,
+            servico,
+            envelope_fn,
+        )
+        .await
+    }
+
+    async fn execute<F>(
+        cli: &dyn Transport,
+        ws_builder: WebServicesBuilder,
+        modelo: Modelo,
+        uf: Uf,
+        ambiente: Ambiente,
+        servico: Servico,
+        envelope_fn: F,
+    ) -> DfeResult
     where
         F: FnOnce(u8, u8, &str, &str) -> String,
     {
         let operacao = servico.operacao().ok_or(DfeError::OperacaoInexistente)?;
-        let dfe = self.with_ws_builder(|ws_builder| {
-            ws_builder
-                .set_modelo(modelo)
-                .set_uf(uf)
-                .set_ambiente(ambiente)
-                .set_servico(servico)
-        });
-        let ws_builder = dfe.webservices_builder;
-        let cli_builder = dfe.client_builder;
-        let ws = ws_builder.build()?;
-        let cli = cli_builder.build()?;
+        let ws = ws_builder
+            .set_modelo(modelo)
+            .set_uf(uf)
+            .set_ambiente(ambiente)
+            .set_servico(servico)
+            .build()?;
         let xml = soap12::format_xml(
             envelope_fn(
                 uf.cuf(),
@@ -177,16 +884,35 @@ impl Dfe {
             )
             .as_str(),
         );
-        let retorno = cli
-            .execute(
-                ws.as_str(),
-                soap12::format_action(operacao).as_str(),
-                xml.as_bytes().to_vec(),
-            )
-            .await?;
+        let action = if servico == Servico::DistribuicaoDfe {
+            soap12::format_action_dist_dfe(operacao)
+        } else {
+            soap12::format_action(operacao)
+        };
+        let xml = xml.into_bytes();
+        let retorno = if servico.idempotente() {
+            cli.execute(ws.as_str(), action.as_str(), xml).await?
+        } else {
+            cli.execute_nao_idempotente(ws.as_str(), action.as_str(), xml)
+                .await?
+        };
         Ok(Xml(retorno))
     }
 
+    #[inline]
+    fn transport(&self) -> result::Result<Arc<dyn Transport>, DfeError> {
+        if let Some(transport) = &self.transport {
+            return Ok(transport.clone());
+        }
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+        // Em requisições simultâneas o client pode ser construído mais de uma vez, mas apenas o
+        // primeiro é mantido.
+        let client = Arc::new(self.client_builder.clone().build()?);
+        Ok(self.client.get_or_init(|| client).clone())
+    }
+
     #[inline]
     fn with_ws_builder<F>(mut self, func: F) -> Self
     where
@@ -202,6 +928,331 @@ impl Dfe {
         F: FnOnce(ClientBuilder) -> ClientBuilder,
     {
         self.client_builder = func(self.client_builder);
+        self.client = Arc::default();
         self
     }
 }
+
+#[inline]
+fn validar_lote(
+    id_lote: &str,
+    processamento: Processamento,
+    nfes: &[&str],
+) -> result::Result<(), DfeError> {
+    if !util::validar_lote(id_lote) {
+        return Err(DfeError::LoteInvalido(id_lote.to_string()));
+    }
+    // Processamento síncrono só é permitido para lotes com uma única nota.
+    if nfes.is_empty()
+        || nfes.len() > MAX_NOTAS_LOTE
+        || (processamento == Processamento::Sincrono && nfes.len() > 1)
+    {
+        return Err(DfeError::QuantidadeNotasInvalida(nfes.len()));
+    }
+    Ok(())
+}
+
+/// Valida chave de nota, extraindo dela o modelo e a UF do emitente usados nos eventos.
+#[inline]
+fn dados_chave(chave: &str) -> result::Result<(Modelo, Uf), DfeError> {
+    match (util::extrair_modelo(chave), util::extrair_uf(chave)) {
+        (Some(modelo), Some(uf)) if util::validar_chave(chave) => Ok((modelo, uf)),
+        _ => Err(DfeError::ChaveInvalida(chave.to_string())),
+    }
+}
+
+/// Dados de NF-e usados para registro de EPEC.
+struct DadosNfe {
+    chave: String,
+    c_uf: String,
+    modelo: String,
+    tp_emis: String,
+    tp_nf: String,
+    dh_emi: String,
+    emit: String,
+    emit_tag: &'static str,
+    emit_ie: String,
+    dest: String,
+    dest_tag: &'static str,
+    dest_uf: String,
+    dest_ie: Option<String>,
+    v_nf: String,
+    v_icms: String,
+    v_st: String,
+}
+
+impl DadosNfe {
+    fn from_xml(xml: &str) -> RetornoResult<Self> {
+        let doc = Document::parse(xml)?;
+        let inf = doc
+            .descendants()
+            .find(|node| node.tag_name().name() == "infNFe")
+            .ok_or(RetornoError::TagNaoEncontrada("infNFe"))?;
+        let chave = inf
+            .attribute("Id")
+            .and_then(|id| id.strip_prefix("NFe"))
+            .ok_or(RetornoError::TagNaoEncontrada("Id"))?;
+        let ide = retorno::elemento(inf, "ide")?;
+        let emit = retorno::elemento(inf, "emit")?;
+        let dest = retorno::elemento(inf, "dest")?;
+        let total = retorno::elemento(retorno::elemento(inf, "total")?, "ICMSTot")?;
+        let (emit_tag, emit_doc) = ["CNPJ", "CPF"]
+            .into_iter()
+            .find_map(|tag| retorno::texto(emit, tag).map(|doc| (tag, doc)))
+            .ok_or(RetornoError::TagNaoEncontrada("CNPJ"))?;
+        // Destinatário pode ser identificado por CNPJ, CPF ou, se estrangeiro, por idEstrangeiro.
+        let (dest_tag, dest_doc) = ["CNPJ", "CPF", "idEstrangeiro"]
+            .into_iter()
+            .find_map(|tag| retorno::texto(dest, tag).map(|doc| (tag, doc)))
+            .ok_or(RetornoError::TagNaoEncontrada("CNPJ"))?;
+        Ok(Self {
+            chave: chave.to_string(),
+            c_uf: retorno::obrigatorio(ide, "cUF")?,
+            modelo: retorno::obrigatorio(ide, "mod")?,
+            tp_emis: retorno::obrigatorio(ide, "tpEmis")?,
+            tp_nf: retorno::obrigatorio(ide, "tpNF")?,
+            dh_emi: retorno::obrigatorio(ide, "dhEmi")?,
+            emit: emit_doc,
+            emit_tag,
+            emit_ie: retorno::obrigatorio(emit, "IE")?,
+            dest: dest_doc,
+            dest_tag,
+            dest_uf: retorno::filho(dest, "enderDest")
+                .and_then(|ender| retorno::texto(ender, "UF"))
+                .unwrap_or_else(|| "EX".to_string()),
+            dest_ie: retorno::texto(dest, "IE").filter(|ie| !ie.is_empty()),
+            v_nf: retorno::obrigatorio(total, "vNF")?,
+            v_icms: retorno::obrigatorio(total, "vICMS")?,
+            v_st: retorno::obrigatorio(total, "vST")?,
+        })
+    }
+}
+
+#[inline]
+fn validar_epec(nfe: &DadosNfe) -> result::Result<(), DfeError> {
+    match nfe.modelo.parse().ok().and_then(Modelo::from_codigo) {
+        Some(Modelo::Nfe) => {}
+        Some(modelo) => return Err(DfeError::ModeloNaoSuportado(modelo)),
+        None => return Err(DfeError::DocumentoInvalido(nfe.modelo.clone())),
+    }
+    // 4 - Contingência EPEC
+    if nfe.tp_emis != "4" {
+        return Err(DfeError::TipoEmissaoInvalido(nfe.tp_emis.clone()));
+    }
+    // UF e modelo da chave devem ser os mesmos informados na identificação da nota.
+    if !util::validar_chave(&nfe.chave)
+        || nfe.chave.get(..2) != Some(nfe.c_uf.as_str())
+        || nfe.chave.get(20..22) != Some(nfe.modelo.as_str())
+    {
+        return Err(DfeError::ChaveInvalida(nfe.chave.clone()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lote() {
+        let nfes = vec!["<NFe/>"; MAX_NOTAS_LOTE + 1];
+        assert!(validar_lote("1", Processamento::Assincrono, &nfes[..MAX_NOTAS_LOTE]).is_ok());
+        assert!(validar_lote("1", Processamento::Sincrono, &nfes[..1]).is_ok());
+        assert!(matches!(
+            validar_lote("1", Processamento::Sincrono, &nfes[..2]),
+            Err(DfeError::QuantidadeNotasInvalida(2))
+        ));
+        assert!(matches!(
+            validar_lote("1", Processamento::Assincrono, &nfes),
+            Err(DfeError::QuantidadeNotasInvalida(51))
+        ));
+        assert!(matches!(
+            validar_lote("1", Processamento::Assincrono, &[]),
+            Err(DfeError::QuantidadeNotasInvalida(0))
+        ));
+        assert!(matches!(
+            validar_lote("1234567890123456", Processamento::Sincrono, &nfes[..1]),
+            Err(DfeError::LoteInvalido(_))
+        ));
+        assert!(matches!(
+            validar_lote("A1", Processamento::Sincrono, &nfes[..1]),
+            Err(DfeError::LoteInvalido(_))
+        ));
+    }
+
+    #[test]
+    fn chave() {
+        assert_eq!(
+            dados_chave("51240111222333000181550010000000011000000010").unwrap(),
+            (Modelo::Nfe, Uf::Mt)
+        );
+        assert_eq!(
+            dados_chave("35240111222333000181650010000000011000000014").unwrap(),
+            (Modelo::Nfce, Uf::Sp)
+        );
+        for chave in [
+            "5124011122233300018155001000000001100000001",
+            "99240111222333000181550010000000011000000010",
+            "51240111222333000181570010000000011000000010",
+            "5124011122233300018155001000000001100000001a",
+        ] {
+            assert!(matches!(dados_chave(chave), Err(DfeError::ChaveInvalida(c)) if c == chave));
+        }
+    }
+
+    fn nfe_epec(ide: &str, dest: &str) -> String {
+        format!(
+            concat!(
+                "<NFe xmlns=\"http://www.portalfiscal.inf.br/nfe\">",
+                "<infNFe Id=\"NFe51240111222333000181550010000000011000000010\" versao=\"4.00\">",
+                "<ide>{}<dhEmi>2024-01-10T10:00:00-04:00</dhEmi><tpNF>1</tpNF></ide>",
+                "<emit><CPF>52998224725</CPF><IE>131234565</IE></emit>",
+                "<dest>{}</dest>",
+                "<total><ICMSTot><vICMS>18.00</vICMS><vST>0.00</vST><vNF>100.00</vNF></ICMSTot></total>",
+                "</infNFe>",
+                "</NFe>"
+            ),
+            ide, dest
+        )
+    }
+
+    #[test]
+    fn dados_nfe() {
+        let ide = "<cUF>51</cUF><mod>55</mod><tpEmis>4</tpEmis>";
+        let nfe = DadosNfe::from_xml(&nfe_epec(
+            ide,
+            "<CNPJ>00000000000191</CNPJ><enderDest><UF>SP</UF></enderDest><IE>ISENTO</IE>",
+        ))
+        .unwrap();
+        assert_eq!(nfe.chave, "51240111222333000181550010000000011000000010");
+        assert_eq!((nfe.c_uf.as_str(), nfe.modelo.as_str()), ("51", "55"));
+        assert_eq!((nfe.emit_tag, nfe.emit.as_str()), ("CPF", "52998224725"));
+        assert_eq!(
+            (nfe.dest_tag, nfe.dest.as_str()),
+            ("CNPJ", "00000000000191")
+        );
+        assert_eq!(nfe.dest_uf, "SP");
+        assert_eq!(nfe.dest_ie.as_deref(), Some("ISENTO"));
+        assert_eq!(nfe.v_nf, "100.00");
+        assert!(validar_epec(&nfe).is_ok());
+
+        // Destinatário estrangeiro não tem endereço com UF nem IE.
+        let nfe = DadosNfe::from_xml(&nfe_epec(ide, "<idEstrangeiro>AB123</idEstrangeiro><IE/>"))
+            .unwrap();
+        assert_eq!(
+            (nfe.dest_tag, nfe.dest_uf.as_str()),
+            ("idEstrangeiro", "EX")
+        );
+        assert_eq!(nfe.dest_ie, None);
+
+        assert!(matches!(
+            DadosNfe::from_xml(&nfe_epec("<cUF>51</cUF><mod>55</mod>", "<CPF>1</CPF>")),
+            Err(RetornoError::TagNaoEncontrada("tpEmis"))
+        ));
+    }
+
+    #[test]
+    fn epec() {
+        let validar = |ide: &str| {
+            validar_epec(&DadosNfe::from_xml(&nfe_epec(ide, "<CPF>52998224725</CPF>")).unwrap())
+        };
+        assert!(validar("<cUF>51</cUF><mod>55</mod><tpEmis>4</tpEmis>").is_ok());
+        assert!(matches!(
+            validar("<cUF>51</cUF><mod>65</mod><tpEmis>4</tpEmis>"),
+            Err(DfeError::ModeloNaoSuportado(Modelo::Nfce))
+        ));
+        assert!(matches!(
+            validar("<cUF>51</cUF><mod>99</mod><tpEmis>4</tpEmis>"),
+            Err(DfeError::DocumentoInvalido(modelo)) if modelo == "99"
+        ));
+        assert!(matches!(
+            validar("<cUF>51</cUF><mod>55</mod><tpEmis>1</tpEmis>"),
+            Err(DfeError::TipoEmissaoInvalido(_))
+        ));
+        assert!(matches!(
+            validar("<cUF>35</cUF><mod>55</mod><tpEmis>4</tpEmis>"),
+            Err(DfeError::ChaveInvalida(_))
+        ));
+    }
+
+    #[test]
+    fn rejeicao() {
+        let xml = Xml(concat!(
+            "<retEnviNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\">",
+            "<cStat>225</cStat><xMotivo>Rejeição: Falha no Schema XML do lote de NFe</xMotivo>",
+            "</retEnviNFe>"
+        )
+        .as_bytes()
+        .to_vec());
+        let err = xml.rejeicao(
+            225,
+            "Rejeição: Falha no Schema XML do lote de NFe".to_string(),
+        );
+        assert_eq!(
+            err.to_string(),
+            "Rejeição 225: Rejeição: Falha no Schema XML do lote de NFe"
+        );
+        assert!(matches!(&err, DfeError::Rejeicao { xml: corpo, .. } if *corpo == xml.to_string()));
+        assert_eq!(err.classificacao(), Some(ClassificacaoStatus::Rejeicao));
+        assert_eq!(
+            xml.rejeicao(108, String::new()).classificacao(),
+            Some(ClassificacaoStatus::Transitoria)
+        );
+        assert_eq!(
+            xml.rejeicao(110, String::new()).classificacao(),
+            Some(ClassificacaoStatus::Denegacao)
+        );
+        assert_eq!(DfeError::JustificativaInvalida.classificacao(), None);
+        assert_eq!(
+            DfeError::LoteEmProcessamento("511000000000001".to_string()).classificacao(),
+            None
+        );
+    }
+
+    #[test]
+    fn xml_retorno() {
+        let xml = Xml(b"<retConsStatServ><cStat>107</cStat></retConsStatServ>".to_vec());
+        assert!(matches!(
+            xml.retorno::<RetEnvEvento>(),
+            Err(RetornoError::TagNaoEncontrada("retEnvEvento"))
+        ));
+        assert!(matches!(
+            Xml(vec![0xff]).retorno::<RetEnvEvento>(),
+            Err(RetornoError::Utf8(_))
+        ));
+        assert_eq!(Xml(vec![b'a', 0xff]).to_string(), "a\u{fffd}");
+    }
+
+    fn mesmo_transporte(a: &Arc<dyn Transport>, b: &Arc<dyn Transport>) -> bool {
+        Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const ()
+    }
+
+    #[test]
+    fn client_reutilizado() {
+        let dfe = Dfe::new();
+        let transport = dfe.transport().unwrap();
+        assert!(mesmo_transporte(&transport, &dfe.transport().unwrap()));
+        // Clones compartilham o client já construído.
+        assert!(mesmo_transporte(
+            &transport,
+            &dfe.clone().transport().unwrap()
+        ));
+    }
+
+    #[test]
+    fn client_reconstruido() {
+        let dfe = Dfe::new();
+        let transport = dfe.transport().unwrap();
+        let dfe = dfe.set_tentativas(Tentativas::new(3));
+        let novo = dfe.transport().unwrap();
+        assert!(!mesmo_transporte(&transport, &novo));
+        assert!(mesmo_transporte(&novo, &dfe.transport().unwrap()));
+    }
+
+    #[test]
+    fn client_invalido() {
+        let dfe = Dfe::new().set_proxy(Proxy::new("não é url"));
+        assert!(matches!(dfe.transport(), Err(DfeError::Client(_))));
+        assert!(dfe.client.get().is_none());
+    }
+}
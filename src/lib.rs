@@ -2,7 +2,9 @@
 //!
 //! ## Exemplo
 //!
-//! O exemplo abaixo mostra como obter o _status_ do serviço de homologação para o Mato Grosso:
+//! O exemplo abaixo mostra como obter o _status_ do serviço de homologação para o Mato Grosso. O
+//! certificado do webservice é verificado pela cadeia da ICP-Brasil embutida com a _feature_
+//! `embed_icp_brasil`:
 //!
 //! ```no_run
 //! use fiscalidade::{Ambiente, Dfe, Modelo, Pkcs12Certificate, Uf, WebServices};
@@ -22,18 +24,37 @@
 //! }
 //! ```
 
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("habilite a feature `native-tls` ou `rustls`");
+
+mod assinatura;
 mod client;
 mod dfe;
 mod pkcs12;
+mod retorno;
+#[cfg(feature = "schemas")]
+mod schema;
+mod sincronizacao;
 mod soap12;
+mod status;
 mod tipos;
+#[cfg(feature = "rustls")]
+mod tls;
 mod util;
 mod webservices;
 
+pub use async_trait::async_trait;
+
+pub use crate::assinatura::*;
 pub use crate::client::*;
 pub use crate::dfe::*;
 pub use crate::pkcs12::*;
+pub use crate::retorno::*;
+#[cfg(feature = "schemas")]
+pub use crate::schema::*;
+pub use crate::sincronizacao::*;
 pub use crate::soap12::*;
+pub use crate::status::*;
 pub use crate::tipos::*;
 pub use crate::util::*;
 pub use crate::webservices::*;
@@ -1,12 +1,27 @@
-use std::{io, path::Path, result};
+use std::{fmt, io, path::Path, result, sync::Arc};
 
+use p12_keystore::KeyStore;
+#[cfg(not(feature = "rustls"))]
 use reqwest::Identity;
+use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey};
 use thiserror::Error;
 use tokio::fs;
 
+#[cfg(feature = "rustls")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+
 /// Objeto para manipulação de certificados PKCS #12.
-#[derive(Debug)]
-pub struct Pkcs12Certificate(Identity);
+#[derive(Clone)]
+pub struct Pkcs12Certificate {
+    #[cfg(not(feature = "rustls"))]
+    identity: Identity,
+    #[cfg(feature = "rustls")]
+    cadeia: Arc<[CertificateDer<'static>]>,
+    #[cfg(feature = "rustls")]
+    chave_pkcs8: Arc<[u8]>,
+    chave: Arc<RsaPrivateKey>,
+    certificado: Arc<[u8]>,
+}
 
 /// Tipo para tratar erros relacionados a I/O e leitura de certificado PKCS #12.
 #[derive(Error, Debug)]
@@ -17,6 +32,15 @@ pub enum Pkcs12CertificateError {
     /// Erros relacionados a leitura de certificado PKCS #12.
     #[error(transparent)]
     Pkcs12(#[from] reqwest::Error),
+    /// Erros relacionados a descriptografia do conteúdo do certificado PKCS #12.
+    #[error(transparent)]
+    KeyStore(#[from] p12_keystore::error::Error),
+    /// Erros relacionados a leitura da chave privada RSA.
+    #[error(transparent)]
+    Chave(#[from] rsa::pkcs8::Error),
+    /// Certificado PKCS #12 sem chave privada ou sem certificado associado.
+    #[error("Chave privada não encontrada no certificado PKCS #12")]
+    ChaveNaoEncontrada,
 }
 
 /// Tipo para tratar retorno de leitura do certificado PKCS #12.
@@ -25,8 +49,27 @@ pub type Pkcs12CertificateResult = result::Result<Pkcs12Certificate, Pkcs12Certi
 impl Pkcs12Certificate {
     #[inline]
     fn from(bytes: &[u8], password: &str) -> Pkcs12CertificateResult {
+        let keystore = KeyStore::from_pkcs12(bytes, password)?;
+        let (_, chain) = keystore
+            .private_key_chain()
+            .ok_or(Pkcs12CertificateError::ChaveNaoEncontrada)?;
+        let certificado = chain
+            .chain()
+            .first()
+            .ok_or(Pkcs12CertificateError::ChaveNaoEncontrada)?;
         Ok(Self {
-            0: Identity::from_pkcs12_der(&bytes, password)?,
+            #[cfg(not(feature = "rustls"))]
+            identity: Identity::from_pkcs12_der(bytes, password)?,
+            #[cfg(feature = "rustls")]
+            cadeia: chain
+                .chain()
+                .iter()
+                .map(|certificado| CertificateDer::from(certificado.as_der().to_vec()))
+                .collect(),
+            #[cfg(feature = "rustls")]
+            chave_pkcs8: chain.key().into(),
+            chave: Arc::new(RsaPrivateKey::from_pkcs8_der(chain.key())?),
+            certificado: certificado.as_der().into(),
         })
     }
 
@@ -45,7 +88,33 @@ impl Pkcs12Certificate {
         Self::from(&fs::read(path).await?, password)
     }
 
+    /// Certificado X.509 do titular, codificado em DER.
+    pub fn certificado(&self) -> &[u8] {
+        &self.certificado
+    }
+
+    pub(crate) fn chave(&self) -> &RsaPrivateKey {
+        &self.chave
+    }
+
+    #[cfg(not(feature = "rustls"))]
     pub(crate) fn into_inner(self) -> Identity {
-        self.0
+        self.identity
+    }
+
+    /// Cadeia de certificados e chave privada para autenticação do client no rustls.
+    #[cfg(feature = "rustls")]
+    pub(crate) fn into_rustls(self) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+        (
+            self.cadeia.to_vec(),
+            PrivatePkcs8KeyDer::from(self.chave_pkcs8.to_vec()).into(),
+        )
+    }
+}
+
+impl fmt::Debug for Pkcs12Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Não expõe a chave privada em logs.
+        f.debug_struct("Pkcs12Certificate").finish_non_exhaustive()
     }
 }
@@ -0,0 +1,1148 @@
+use std::{
+    io::{self, Read},
+    result, str,
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::read::GzDecoder;
+use roxmltree::{Document, Node};
+use thiserror::Error;
+
+use crate::tipos::TipoEvento;
+
+/// Tipo para tratar erros relacionados a leitura de XMLs retornados pela SEFAZ.
+#[derive(Error, Debug)]
+pub enum RetornoError {
+    /// Erros relacionados a codificação do XML.
+    #[error(transparent)]
+    Utf8(#[from] str::Utf8Error),
+    /// Erros relacionados a XML mal formado.
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    /// Tag obrigatória ausente no XML retornado.
+    #[error("Tag não encontrada no retorno: {0}")]
+    TagNaoEncontrada(&'static str),
+    /// Erros relacionados a decodificação de documentos compactados.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Erros relacionados a decodificação de conteúdo em base64.
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    /// Tag com conteúdo que não pôde ser convertido.
+    #[error("Valor inválido para tag {tag}: {valor}")]
+    ValorInvalido { tag: &'static str, valor: String },
+    /// Conteúdo descompactado da tag maior que o limite aceito.
+    #[error("Conteúdo da tag {tag} excede o limite de {limite} bytes")]
+    TamanhoExcedido { tag: &'static str, limite: u64 },
+}
+
+/// Tipo para tratar retorno de leitura de XMLs retornados pela SEFAZ.
+pub type RetornoResult<T> = result::Result<T, RetornoError>;
+
+/// Retorno tipado de um serviço da SEFAZ, extraído do envelope SOAP.
+pub trait Retorno: Sized {
+    /// Nome da tag raiz do retorno, e.g. `retEnviNFe`.
+    const TAG: &'static str;
+
+    /// Lê o retorno a partir do nó de sua tag raiz.
+    fn from_node(node: Node) -> RetornoResult<Self>;
+
+    /// Lê o retorno a partir de XML, localizando sua tag raiz em qualquer nível do documento.
+    fn from_xml(xml: &str) -> RetornoResult<Self> {
+        let doc = Document::parse(xml)?;
+        let node = doc
+            .descendants()
+            .find(|node| node.tag_name().name() == Self::TAG)
+            .ok_or(RetornoError::TagNaoEncontrada(Self::TAG))?;
+        Self::from_node(node)
+    }
+}
+
+#[inline]
+pub(crate) fn filho<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == tag)
+}
+
+#[inline]
+pub(crate) fn elemento<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'static str,
+) -> RetornoResult<Node<'a, 'input>> {
+    filho(node, tag).ok_or(RetornoError::TagNaoEncontrada(tag))
+}
+
+#[inline]
+pub(crate) fn filhos<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.tag_name().name() == tag)
+}
+
+#[inline]
+pub(crate) fn texto(node: Node, tag: &str) -> Option<String> {
+    filho(node, tag).map(|n| n.text().unwrap_or_default().trim().to_string())
+}
+
+#[inline]
+pub(crate) fn obrigatorio(node: Node, tag: &'static str) -> RetornoResult<String> {
+    texto(node, tag).ok_or(RetornoError::TagNaoEncontrada(tag))
+}
+
+#[inline]
+pub(crate) fn numero<T: FromStr>(node: Node, tag: &'static str) -> RetornoResult<Option<T>> {
+    texto(node, tag)
+        .map(|valor| {
+            valor
+                .parse()
+                .map_err(|_| RetornoError::ValorInvalido { tag, valor })
+        })
+        .transpose()
+}
+
+#[inline]
+pub(crate) fn c_stat(node: Node) -> RetornoResult<u16> {
+    numero(node, "cStat")?.ok_or(RetornoError::TagNaoEncontrada("cStat"))
+}
+
+#[inline]
+pub(crate) fn conteudo(node: Node) -> String {
+    node.document().input_text()[node.range()].to_string()
+}
+
+/// Retorno da consulta de status do serviço (`retConsStatServ`).
+#[derive(Clone, Debug)]
+pub struct RetConsStatServ {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub c_uf: String,
+    pub dh_recbto: String,
+    /// Tempo médio de resposta do serviço, em segundos.
+    pub t_med: Option<u32>,
+    /// Previsão de retorno do serviço quando paralisado.
+    pub dh_retorno: Option<String>,
+    pub x_obs: Option<String>,
+}
+
+impl RetConsStatServ {
+    /// Verifica se o serviço está em operação (cStat 107).
+    pub fn em_operacao(&self) -> bool {
+        self.c_stat == 107
+    }
+}
+
+impl Retorno for RetConsStatServ {
+    const TAG: &'static str = "retConsStatServ";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        Ok(Self {
+            tp_amb: obrigatorio(node, "tpAmb")?,
+            ver_aplic: obrigatorio(node, "verAplic")?,
+            c_stat: c_stat(node)?,
+            x_motivo: obrigatorio(node, "xMotivo")?,
+            c_uf: obrigatorio(node, "cUF")?,
+            dh_recbto: obrigatorio(node, "dhRecbto")?,
+            t_med: numero(node, "tMed")?,
+            dh_retorno: texto(node, "dhRetorno"),
+            x_obs: texto(node, "xObs"),
+        })
+    }
+}
+
+/// Protocolo de autorização (`protNFe`) de uma nota.
+#[derive(Clone, Debug)]
+pub struct ProtNFe {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub ch_nfe: String,
+    pub dh_recbto: String,
+    pub n_prot: Option<String>,
+    pub dig_val: Option<String>,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    /// XML original do protocolo, usado para montar o `nfeProc`.
+    pub xml: String,
+}
+
+impl Retorno for ProtNFe {
+    const TAG: &'static str = "protNFe";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let inf = filho(node, "infProt").ok_or(RetornoError::TagNaoEncontrada("infProt"))?;
+        Ok(Self {
+            tp_amb: obrigatorio(inf, "tpAmb")?,
+            ver_aplic: obrigatorio(inf, "verAplic")?,
+            ch_nfe: obrigatorio(inf, "chNFe")?,
+            dh_recbto: obrigatorio(inf, "dhRecbto")?,
+            n_prot: texto(inf, "nProt"),
+            dig_val: texto(inf, "digVal"),
+            c_stat: c_stat(inf)?,
+            x_motivo: obrigatorio(inf, "xMotivo")?,
+            xml: conteudo(node),
+        })
+    }
+}
+
+/// Retorno do envio de lote (`retEnviNFe`).
+///
+/// Contém o recibo (`nRec`) quando o lote é processado de forma assíncrona ou o protocolo da nota
+/// quando processado de forma síncrona.
+#[derive(Clone, Debug)]
+pub struct RetEnviNFe {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub c_uf: String,
+    pub dh_recbto: String,
+    pub n_rec: Option<String>,
+    /// Tempo médio de resposta do serviço, em segundos.
+    pub t_med: Option<u32>,
+    pub prot_nfe: Option<ProtNFe>,
+}
+
+impl Retorno for RetEnviNFe {
+    const TAG: &'static str = "retEnviNFe";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let inf_rec = filho(node, "infRec");
+        Ok(Self {
+            tp_amb: obrigatorio(node, "tpAmb")?,
+            ver_aplic: obrigatorio(node, "verAplic")?,
+            c_stat: c_stat(node)?,
+            x_motivo: obrigatorio(node, "xMotivo")?,
+            c_uf: obrigatorio(node, "cUF")?,
+            dh_recbto: obrigatorio(node, "dhRecbto")?,
+            n_rec: inf_rec.and_then(|inf| texto(inf, "nRec")),
+            t_med: inf_rec
+                .map(|inf| numero(inf, "tMed"))
+                .transpose()?
+                .flatten(),
+            prot_nfe: filho(node, "protNFe").map(ProtNFe::from_node).transpose()?,
+        })
+    }
+}
+
+/// Retorno da consulta de recibo de lote (`retConsReciNFe`).
+#[derive(Clone, Debug)]
+pub struct RetConsReciNFe {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub n_rec: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub c_uf: String,
+    pub dh_recbto: Option<String>,
+    pub prot_nfe: Vec<ProtNFe>,
+}
+
+impl Retorno for RetConsReciNFe {
+    const TAG: &'static str = "retConsReciNFe";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        Ok(Self {
+            tp_amb: obrigatorio(node, "tpAmb")?,
+            ver_aplic: obrigatorio(node, "verAplic")?,
+            n_rec: obrigatorio(node, "nRec")?,
+            c_stat: c_stat(node)?,
+            x_motivo: obrigatorio(node, "xMotivo")?,
+            c_uf: obrigatorio(node, "cUF")?,
+            dh_recbto: texto(node, "dhRecbto"),
+            prot_nfe: filhos(node, "protNFe")
+                .map(ProtNFe::from_node)
+                .collect::<RetornoResult<_>>()?,
+        })
+    }
+}
+
+/// Resultado do processamento de um evento (`retEvento`).
+#[derive(Clone, Debug)]
+pub struct RetEvento {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_orgao: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub ch_nfe: Option<String>,
+    pub tp_evento: Option<String>,
+    pub x_evento: Option<String>,
+    pub n_seq_evento: Option<u8>,
+    pub dh_reg_evento: Option<String>,
+    pub n_prot: Option<String>,
+    /// XML original do resultado, usado para montar o `procEventoNFe`.
+    pub xml: String,
+}
+
+impl Retorno for RetEvento {
+    const TAG: &'static str = "retEvento";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let inf = filho(node, "infEvento").ok_or(RetornoError::TagNaoEncontrada("infEvento"))?;
+        Ok(Self {
+            tp_amb: obrigatorio(inf, "tpAmb")?,
+            ver_aplic: obrigatorio(inf, "verAplic")?,
+            c_orgao: obrigatorio(inf, "cOrgao")?,
+            c_stat: c_stat(inf)?,
+            x_motivo: obrigatorio(inf, "xMotivo")?,
+            ch_nfe: texto(inf, "chNFe"),
+            tp_evento: texto(inf, "tpEvento"),
+            x_evento: texto(inf, "xEvento"),
+            n_seq_evento: numero(inf, "nSeqEvento")?,
+            dh_reg_evento: texto(inf, "dhRegEvento"),
+            n_prot: texto(inf, "nProt"),
+            xml: conteudo(node),
+        })
+    }
+}
+
+/// Evento com protocolo de registro (`procEventoNFe`).
+#[derive(Clone, Debug)]
+pub struct ProcEventoNFe {
+    pub ch_nfe: String,
+    pub tp_evento: String,
+    pub n_seq_evento: Option<u8>,
+    pub dh_evento: String,
+    pub ret_evento: RetEvento,
+    /// XML original do evento com o protocolo.
+    pub xml: String,
+}
+
+impl ProcEventoNFe {
+    /// Verifica se é um cancelamento, inclusive por substituição, registrado pela SEFAZ.
+    pub fn cancelamento(&self) -> bool {
+        let cancelamento = [
+            TipoEvento::Cancelamento.codigo(),
+            TipoEvento::CancelamentoSubstituicao.codigo(),
+        ];
+        // 135 - Evento registrado e vinculado a NF-e, 155 - Cancelamento homologado fora de prazo
+        matches!(self.tp_evento.parse(), Ok(tp_evento) if cancelamento.contains(&tp_evento))
+            && matches!(self.ret_evento.c_stat, 135 | 155)
+    }
+}
+
+impl Retorno for ProcEventoNFe {
+    const TAG: &'static str = "procEventoNFe";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let inf = elemento(elemento(node, "evento")?, "infEvento")?;
+        Ok(Self {
+            ch_nfe: obrigatorio(inf, "chNFe")?,
+            tp_evento: obrigatorio(inf, "tpEvento")?,
+            n_seq_evento: numero(inf, "nSeqEvento")?,
+            dh_evento: obrigatorio(inf, "dhEvento")?,
+            ret_evento: RetEvento::from_node(elemento(node, "retEvento")?)?,
+            xml: conteudo(node),
+        })
+    }
+}
+
+/// Situação atual de uma nota, conforme consulta de protocolo.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SituacaoNFe {
+    Autorizada,
+    Cancelada,
+    Denegada,
+}
+
+/// Retorno da consulta de situação da nota (`retConsSitNFe`).
+#[derive(Clone, Debug)]
+pub struct RetConsSitNFe {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub c_uf: String,
+    pub dh_recbto: String,
+    pub ch_nfe: String,
+    pub prot_nfe: Option<ProtNFe>,
+    pub proc_evento_nfe: Vec<ProcEventoNFe>,
+}
+
+impl RetConsSitNFe {
+    /// Evento de cancelamento registrado para a nota, se houver.
+    pub fn cancelamento(&self) -> Option<&ProcEventoNFe> {
+        self.proc_evento_nfe
+            .iter()
+            .find(|proc_evento| proc_evento.cancelamento())
+    }
+
+    /// Situação atual da nota ou `None` se ela não foi localizada ou a consulta foi rejeitada.
+    pub fn situacao(&self) -> Option<SituacaoNFe> {
+        match self.c_stat {
+            // 101 - Cancelamento homologado, 151 - Cancelamento homologado fora de prazo
+            101 | 151 => Some(SituacaoNFe::Cancelada),
+            _ if self.cancelamento().is_some() => Some(SituacaoNFe::Cancelada),
+            // 100 - Autorizado o uso, 150 - Autorizado o uso, autorização concedida fora de prazo
+            100 | 150 => Some(SituacaoNFe::Autorizada),
+            // 110 - Uso denegado, 301/302/303 - Uso denegado por irregularidade fiscal
+            110 | 301 | 302 | 303 => Some(SituacaoNFe::Denegada),
+            _ => None,
+        }
+    }
+}
+
+impl Retorno for RetConsSitNFe {
+    const TAG: &'static str = "retConsSitNFe";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        Ok(Self {
+            tp_amb: obrigatorio(node, "tpAmb")?,
+            ver_aplic: obrigatorio(node, "verAplic")?,
+            c_stat: c_stat(node)?,
+            x_motivo: obrigatorio(node, "xMotivo")?,
+            c_uf: obrigatorio(node, "cUF")?,
+            dh_recbto: obrigatorio(node, "dhRecbto")?,
+            ch_nfe: obrigatorio(node, "chNFe")?,
+            prot_nfe: filho(node, "protNFe").map(ProtNFe::from_node).transpose()?,
+            proc_evento_nfe: filhos(node, "procEventoNFe")
+                .map(ProcEventoNFe::from_node)
+                .collect::<RetornoResult<_>>()?,
+        })
+    }
+}
+
+/// Retorno do envio de lote de eventos (`retEnvEvento`).
+#[derive(Clone, Debug)]
+pub struct RetEnvEvento {
+    pub id_lote: String,
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_orgao: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub ret_evento: Vec<RetEvento>,
+}
+
+impl Retorno for RetEnvEvento {
+    const TAG: &'static str = "retEnvEvento";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        Ok(Self {
+            id_lote: obrigatorio(node, "idLote")?,
+            tp_amb: obrigatorio(node, "tpAmb")?,
+            ver_aplic: obrigatorio(node, "verAplic")?,
+            c_orgao: obrigatorio(node, "cOrgao")?,
+            c_stat: c_stat(node)?,
+            x_motivo: obrigatorio(node, "xMotivo")?,
+            ret_evento: filhos(node, "retEvento")
+                .map(RetEvento::from_node)
+                .collect::<RetornoResult<_>>()?,
+        })
+    }
+}
+
+/// Retorno do pedido de inutilização de numeração (`retInutNFe`).
+#[derive(Clone, Debug)]
+pub struct RetInutNFe {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub c_uf: String,
+    pub ano: Option<String>,
+    pub cnpj: Option<String>,
+    pub modelo: Option<String>,
+    pub serie: Option<String>,
+    pub n_nf_ini: Option<String>,
+    pub n_nf_fin: Option<String>,
+    pub dh_recbto: Option<String>,
+    pub n_prot: Option<String>,
+    /// XML original do retorno, usado para montar o `procInutNFe`.
+    pub xml: String,
+}
+
+impl Retorno for RetInutNFe {
+    const TAG: &'static str = "retInutNFe";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let inf = filho(node, "infInut").ok_or(RetornoError::TagNaoEncontrada("infInut"))?;
+        Ok(Self {
+            tp_amb: obrigatorio(inf, "tpAmb")?,
+            ver_aplic: obrigatorio(inf, "verAplic")?,
+            c_stat: c_stat(inf)?,
+            x_motivo: obrigatorio(inf, "xMotivo")?,
+            c_uf: obrigatorio(inf, "cUF")?,
+            ano: texto(inf, "ano"),
+            cnpj: texto(inf, "CNPJ"),
+            modelo: texto(inf, "mod"),
+            serie: texto(inf, "serie"),
+            n_nf_ini: texto(inf, "nNFIni"),
+            n_nf_fin: texto(inf, "nNFFin"),
+            dh_recbto: texto(inf, "dhRecbto"),
+            n_prot: texto(inf, "nProt"),
+            xml: conteudo(node),
+        })
+    }
+}
+
+/// Endereço de contribuinte retornado pela consulta de cadastro (`ender`).
+#[derive(Clone, Debug)]
+pub struct Endereco {
+    pub x_lgr: Option<String>,
+    pub nro: Option<String>,
+    pub x_cpl: Option<String>,
+    pub x_bairro: Option<String>,
+    pub c_mun: Option<String>,
+    pub x_mun: Option<String>,
+    pub cep: Option<String>,
+}
+
+impl Endereco {
+    #[inline]
+    fn from_node(node: Node) -> Self {
+        Self {
+            x_lgr: texto(node, "xLgr"),
+            nro: texto(node, "nro"),
+            x_cpl: texto(node, "xCpl"),
+            x_bairro: texto(node, "xBairro"),
+            c_mun: texto(node, "cMun"),
+            x_mun: texto(node, "xMun"),
+            cep: texto(node, "CEP"),
+        }
+    }
+}
+
+/// Dados cadastrais de um contribuinte (`infCad`), um por inscrição estadual.
+#[derive(Clone, Debug)]
+pub struct InfCad {
+    pub ie: String,
+    pub cnpj: Option<String>,
+    pub cpf: Option<String>,
+    pub uf: String,
+    /// Situação do contribuinte: 0 - não habilitado, 1 - habilitado.
+    pub c_sit: String,
+    pub ind_cred_nfe: String,
+    pub ind_cred_cte: String,
+    pub x_nome: String,
+    pub x_fant: Option<String>,
+    pub x_reg_apur: Option<String>,
+    pub cnae: Option<String>,
+    pub d_ini_ativ: Option<String>,
+    pub d_ult_sit: Option<String>,
+    pub d_baixa: Option<String>,
+    pub ie_unica: Option<String>,
+    pub ie_atual: Option<String>,
+    pub ender: Option<Endereco>,
+}
+
+impl Retorno for InfCad {
+    const TAG: &'static str = "infCad";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        Ok(Self {
+            ie: obrigatorio(node, "IE")?,
+            cnpj: texto(node, "CNPJ"),
+            cpf: texto(node, "CPF"),
+            uf: obrigatorio(node, "UF")?,
+            c_sit: obrigatorio(node, "cSit")?,
+            ind_cred_nfe: obrigatorio(node, "indCredNFe")?,
+            ind_cred_cte: obrigatorio(node, "indCredCTe")?,
+            x_nome: obrigatorio(node, "xNome")?,
+            x_fant: texto(node, "xFant"),
+            x_reg_apur: texto(node, "xRegApur"),
+            cnae: texto(node, "CNAE"),
+            d_ini_ativ: texto(node, "dIniAtiv"),
+            d_ult_sit: texto(node, "dUltSit"),
+            d_baixa: texto(node, "dBaixa"),
+            ie_unica: texto(node, "IEUnica"),
+            ie_atual: texto(node, "IEAtual"),
+            ender: filho(node, "ender").map(Endereco::from_node),
+        })
+    }
+}
+
+/// Retorno da consulta de cadastro de contribuintes (`retConsCad`).
+#[derive(Clone, Debug)]
+pub struct RetConsCad {
+    pub ver_aplic: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub uf: String,
+    pub ie: Option<String>,
+    pub cnpj: Option<String>,
+    pub cpf: Option<String>,
+    pub dh_cons: String,
+    pub c_uf: String,
+    pub inf_cad: Vec<InfCad>,
+}
+
+impl Retorno for RetConsCad {
+    const TAG: &'static str = "retConsCad";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let inf = elemento(node, "infCons")?;
+        Ok(Self {
+            ver_aplic: obrigatorio(inf, "verAplic")?,
+            c_stat: c_stat(inf)?,
+            x_motivo: obrigatorio(inf, "xMotivo")?,
+            uf: obrigatorio(inf, "UF")?,
+            ie: texto(inf, "IE"),
+            cnpj: texto(inf, "CNPJ"),
+            cpf: texto(inf, "CPF"),
+            dh_cons: obrigatorio(inf, "dhCons")?,
+            c_uf: obrigatorio(inf, "cUF")?,
+            inf_cad: filhos(inf, "infCad")
+                .map(InfCad::from_node)
+                .collect::<RetornoResult<_>>()?,
+        })
+    }
+}
+
+/// Schema de documento retornado pela distribuição de DF-e.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SchemaDfe {
+    /// Resumo de NF-e.
+    ResNFe,
+    /// NF-e completa com protocolo de autorização.
+    ProcNFe,
+    /// Resumo de evento.
+    ResEvento,
+    /// Evento completo com protocolo de registro.
+    ProcEventoNFe,
+    /// Schema não mapeado, com o nome informado pela SEFAZ.
+    Desconhecido(String),
+}
+
+impl SchemaDfe {
+    /// Identifica schema a partir do atributo `schema`, e.g. `procNFe_v4.00.xsd`.
+    #[inline]
+    pub fn from_schema(schema: &str) -> Self {
+        use SchemaDfe::*;
+        match schema.split('_').next().unwrap_or_default() {
+            "resNFe" => ResNFe,
+            "procNFe" => ProcNFe,
+            "resEvento" => ResEvento,
+            "procEventoNFe" => ProcEventoNFe,
+            _ => Desconhecido(schema.to_string()),
+        }
+    }
+}
+
+/// Tamanho máximo de documento da distribuição de DF-e após descompactado, evitando que um
+/// `docZip` malicioso consuma toda a memória disponível.
+pub const MAX_TAMANHO_DOCUMENTO_DFE: u64 = 10 * 1024 * 1024;
+
+/// Documento retornado pela distribuição de DF-e, já descompactado.
+#[derive(Clone, Debug)]
+pub struct DocumentoDfe {
+    pub nsu: u64,
+    pub schema: SchemaDfe,
+    pub xml: String,
+}
+
+impl Retorno for DocumentoDfe {
+    const TAG: &'static str = "docZip";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        let nsu = node.attribute("NSU").unwrap_or_default();
+        let zip: String = node.text().unwrap_or_default().split_whitespace().collect();
+        let mut xml = Vec::new();
+        GzDecoder::new(BASE64.decode(zip)?.as_slice())
+            .take(MAX_TAMANHO_DOCUMENTO_DFE + 1)
+            .read_to_end(&mut xml)?;
+        if xml.len() as u64 > MAX_TAMANHO_DOCUMENTO_DFE {
+            return Err(RetornoError::TamanhoExcedido {
+                tag: Self::TAG,
+                limite: MAX_TAMANHO_DOCUMENTO_DFE,
+            });
+        }
+        Ok(Self {
+            nsu: nsu.parse().map_err(|_| RetornoError::ValorInvalido {
+                tag: "NSU",
+                valor: nsu.to_string(),
+            })?,
+            schema: SchemaDfe::from_schema(node.attribute("schema").unwrap_or_default()),
+            xml: str::from_utf8(&xml)?.to_string(),
+        })
+    }
+}
+
+/// Retorno da distribuição de DF-e de interesse (`retDistDFeInt`).
+#[derive(Clone, Debug)]
+pub struct RetDistDFeInt {
+    pub tp_amb: String,
+    pub ver_aplic: String,
+    pub c_stat: u16,
+    pub x_motivo: String,
+    pub dh_resp: String,
+    pub ult_nsu: u64,
+    pub max_nsu: u64,
+    pub documentos: Vec<DocumentoDfe>,
+}
+
+impl Retorno for RetDistDFeInt {
+    const TAG: &'static str = "retDistDFeInt";
+
+    fn from_node(node: Node) -> RetornoResult<Self> {
+        Ok(Self {
+            tp_amb: obrigatorio(node, "tpAmb")?,
+            ver_aplic: obrigatorio(node, "verAplic")?,
+            c_stat: c_stat(node)?,
+            x_motivo: obrigatorio(node, "xMotivo")?,
+            dh_resp: obrigatorio(node, "dhResp")?,
+            ult_nsu: numero(node, "ultNSU")?.unwrap_or_default(),
+            max_nsu: numero(node, "maxNSU")?.unwrap_or_default(),
+            documentos: filho(node, "loteDistDFeInt")
+                .map(|lote| {
+                    filhos(lote, "docZip")
+                        .map(DocumentoDfe::from_node)
+                        .collect::<RetornoResult<_>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROT_NFE: &str = concat!(
+        "<protNFe versao=\"4.00\"><infProt>",
+        "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+        "<chNFe>51240111222333000181550010000000011000000010</chNFe>",
+        "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto>",
+        "<nProt>151240000000001</nProt><digVal>abc=</digVal>",
+        "<cStat>100</cStat><xMotivo>Autorizado o uso da NF-e</xMotivo>",
+        "</infProt></protNFe>"
+    );
+
+    #[test]
+    fn ret_envi_nfe_sincrono() {
+        let xml = format!(
+            concat!(
+                "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\"><soap:Body>",
+                "<retEnviNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+                "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+                "<cStat>104</cStat><xMotivo>Lote processado</xMotivo><cUF>51</cUF>",
+                "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto>{}",
+                "</retEnviNFe></soap:Body></soap:Envelope>"
+            ),
+            PROT_NFE
+        );
+        let ret = RetEnviNFe::from_xml(&xml).unwrap();
+        assert_eq!(ret.c_stat, 104);
+        assert_eq!(ret.n_rec, None);
+        let prot = ret.prot_nfe.unwrap();
+        assert_eq!(prot.c_stat, 100);
+        assert_eq!(prot.n_prot.as_deref(), Some("151240000000001"));
+        assert_eq!(prot.xml, PROT_NFE);
+    }
+
+    #[test]
+    fn ret_envi_nfe_assincrono() {
+        let ret = RetEnviNFe::from_xml(concat!(
+            "<retEnviNFe versao=\"4.00\">",
+            "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+            "<cStat>103</cStat><xMotivo>Lote recebido com sucesso</xMotivo><cUF>51</cUF>",
+            "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto>",
+            "<infRec><nRec>510000000000001</nRec><tMed>2</tMed></infRec>",
+            "</retEnviNFe>"
+        ))
+        .unwrap();
+        assert_eq!(ret.n_rec.as_deref(), Some("510000000000001"));
+        assert_eq!(ret.t_med, Some(2));
+        assert!(ret.prot_nfe.is_none());
+    }
+
+    #[test]
+    fn ret_envi_nfe_invalido() {
+        assert!(matches!(
+            RetEnviNFe::from_xml("<retEnviNFe><cStat>103</cStat></retEnviNFe>"),
+            Err(RetornoError::TagNaoEncontrada("tpAmb"))
+        ));
+        assert!(matches!(
+            RetEnviNFe::from_xml("<retConsStatServ/>"),
+            Err(RetornoError::TagNaoEncontrada("retEnviNFe"))
+        ));
+        assert!(matches!(
+            ProtNFe::from_xml(&PROT_NFE.replace("<cStat>100", "<cStat>abc")),
+            Err(RetornoError::ValorInvalido { tag: "cStat", .. })
+        ));
+    }
+
+    #[test]
+    fn ret_cons_reci_nfe() {
+        let ret = RetConsReciNFe::from_xml(&format!(
+            concat!(
+                "<retConsReciNFe versao=\"4.00\">",
+                "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+                "<nRec>510000000000001</nRec>",
+                "<cStat>104</cStat><xMotivo>Lote processado</xMotivo><cUF>51</cUF>",
+                "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto>{0}{1}",
+                "</retConsReciNFe>"
+            ),
+            PROT_NFE,
+            PROT_NFE.replace("<cStat>100", "<cStat>204")
+        ))
+        .unwrap();
+        assert_eq!(ret.n_rec, "510000000000001");
+        assert_eq!(
+            ret.prot_nfe
+                .iter()
+                .map(|prot| prot.c_stat)
+                .collect::<Vec<_>>(),
+            [100, 204]
+        );
+    }
+
+    #[test]
+    fn ret_cons_reci_nfe_em_processamento() {
+        let ret = RetConsReciNFe::from_xml(concat!(
+            "<retConsReciNFe versao=\"4.00\">",
+            "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+            "<nRec>510000000000001</nRec>",
+            "<cStat>105</cStat><xMotivo>Lote em processamento</xMotivo><cUF>51</cUF>",
+            "</retConsReciNFe>"
+        ))
+        .unwrap();
+        assert_eq!(ret.c_stat, 105);
+        assert_eq!(ret.dh_recbto, None);
+        assert!(ret.prot_nfe.is_empty());
+    }
+
+    #[test]
+    fn ret_env_evento() {
+        let ret = RetEnvEvento::from_xml(concat!(
+            "<retEnvEvento versao=\"1.00\">",
+            "<idLote>1</idLote><tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+            "<cOrgao>51</cOrgao><cStat>128</cStat><xMotivo>Lote de Evento Processado</xMotivo>",
+            "<retEvento versao=\"1.00\"><infEvento>",
+            "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic><cOrgao>51</cOrgao>",
+            "<cStat>135</cStat><xMotivo>Evento registrado e vinculado a NF-e</xMotivo>",
+            "<chNFe>51240111222333000181550010000000011000000010</chNFe>",
+            "<tpEvento>110111</tpEvento><xEvento>Cancelamento</xEvento>",
+            "<nSeqEvento>1</nSeqEvento><dhRegEvento>2024-01-10T10:00:00-04:00</dhRegEvento>",
+            "<nProt>151240000000002</nProt>",
+            "</infEvento></retEvento>",
+            "</retEnvEvento>"
+        ))
+        .unwrap();
+        assert_eq!(ret.c_stat, 128);
+        assert_eq!(ret.ret_evento.len(), 1);
+        let evento = &ret.ret_evento[0];
+        assert_eq!(evento.c_stat, 135);
+        assert_eq!(evento.tp_evento.as_deref(), Some("110111"));
+        assert_eq!(evento.n_seq_evento, Some(1));
+        assert_eq!(evento.n_prot.as_deref(), Some("151240000000002"));
+        assert!(evento.xml.starts_with("<retEvento versao=\"1.00\">"));
+    }
+
+    #[test]
+    fn ret_inut_nfe() {
+        let ret = RetInutNFe::from_xml(concat!(
+            "<retInutNFe versao=\"4.00\"><infInut>",
+            "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+            "<cStat>102</cStat><xMotivo>Inutilização de número homologado</xMotivo>",
+            "<cUF>51</cUF><ano>24</ano><CNPJ>11222333000181</CNPJ><mod>55</mod>",
+            "<serie>1</serie><nNFIni>10</nNFIni><nNFFin>20</nNFFin>",
+            "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto><nProt>151240000000003</nProt>",
+            "</infInut></retInutNFe>"
+        ))
+        .unwrap();
+        assert_eq!(ret.c_stat, 102);
+        assert_eq!(ret.n_nf_ini.as_deref(), Some("10"));
+        assert_eq!(ret.n_prot.as_deref(), Some("151240000000003"));
+
+        // Rejeições não trazem os dados da faixa.
+        let ret = RetInutNFe::from_xml(concat!(
+            "<retInutNFe versao=\"4.00\"><infInut>",
+            "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+            "<cStat>241</cStat><xMotivo>Um número da faixa já foi utilizado</xMotivo>",
+            "<cUF>51</cUF>",
+            "</infInut></retInutNFe>"
+        ))
+        .unwrap();
+        assert_eq!(ret.c_stat, 241);
+        assert_eq!(ret.n_prot, None);
+    }
+
+    fn doc_zip(conteudo: &[u8]) -> String {
+        use std::io::Write;
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(conteudo).unwrap();
+        format!(
+            "<docZip NSU=\"000000000000001\" schema=\"resNFe_v1.01.xsd\">{}</docZip>",
+            BASE64.encode(gz.finish().unwrap())
+        )
+    }
+
+    #[test]
+    fn documento_dfe_tamanho_maximo() {
+        let limite = MAX_TAMANHO_DOCUMENTO_DFE as usize;
+        let doc = DocumentoDfe::from_xml(&doc_zip(&vec![b' '; limite])).unwrap();
+        assert_eq!(doc.xml.len(), limite);
+        assert!(matches!(
+            DocumentoDfe::from_xml(&doc_zip(&vec![b' '; limite + 1])),
+            Err(RetornoError::TamanhoExcedido { tag: "docZip", limite: l }) if l == MAX_TAMANHO_DOCUMENTO_DFE
+        ));
+    }
+
+    #[test]
+    fn schema_dfe() {
+        assert_eq!(
+            SchemaDfe::from_schema("resNFe_v1.01.xsd"),
+            SchemaDfe::ResNFe
+        );
+        assert_eq!(
+            SchemaDfe::from_schema("procNFe_v4.00.xsd"),
+            SchemaDfe::ProcNFe
+        );
+        assert_eq!(
+            SchemaDfe::from_schema("resEvento_v1.01.xsd"),
+            SchemaDfe::ResEvento
+        );
+        assert_eq!(
+            SchemaDfe::from_schema("procEventoNFe_v1.00.xsd"),
+            SchemaDfe::ProcEventoNFe
+        );
+        assert_eq!(
+            SchemaDfe::from_schema("resCTe_v1.00.xsd"),
+            SchemaDfe::Desconhecido("resCTe_v1.00.xsd".to_string())
+        );
+    }
+
+    #[test]
+    fn ret_dist_dfe_int() {
+        let xml = format!(
+            concat!(
+                "<retDistDFeInt xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"1.01\">",
+                "<tpAmb>2</tpAmb><verAplic>1.4.0</verAplic>",
+                "<cStat>138</cStat><xMotivo>Documento localizado</xMotivo>",
+                "<dhResp>2024-01-10T10:00:00-03:00</dhResp>",
+                "<ultNSU>000000000000001</ultNSU><maxNSU>000000000000005</maxNSU>",
+                "<loteDistDFeInt>{}</loteDistDFeInt>",
+                "</retDistDFeInt>"
+            ),
+            doc_zip(b"<resNFe/>")
+        );
+        let ret = RetDistDFeInt::from_xml(&xml).unwrap();
+        assert_eq!(ret.c_stat, 138);
+        assert_eq!((ret.ult_nsu, ret.max_nsu), (1, 5));
+        assert_eq!(ret.documentos.len(), 1);
+        assert_eq!(ret.documentos[0].nsu, 1);
+        assert_eq!(ret.documentos[0].schema, SchemaDfe::ResNFe);
+        assert_eq!(ret.documentos[0].xml, "<resNFe/>");
+
+        // Sem documentos, o lote é omitido.
+        let ret = RetDistDFeInt::from_xml(&xml.replace(&doc_zip(b"<resNFe/>"), "")).unwrap();
+        assert!(ret.documentos.is_empty());
+    }
+
+    #[test]
+    fn documento_dfe_invalido() {
+        assert!(matches!(
+            DocumentoDfe::from_xml(&doc_zip(b"<resNFe/>").replace("000000000000001", "x")),
+            Err(RetornoError::ValorInvalido { tag: "NSU", .. })
+        ));
+        assert!(matches!(
+            DocumentoDfe::from_xml("<docZip NSU=\"1\">!!!</docZip>"),
+            Err(RetornoError::Base64(_))
+        ));
+        assert!(matches!(
+            DocumentoDfe::from_xml("<docZip NSU=\"1\">bW9jaw==</docZip>"),
+            Err(RetornoError::Io(_))
+        ));
+        assert!(matches!(
+            DocumentoDfe::from_xml(&doc_zip(&[0xff, 0xfe])),
+            Err(RetornoError::Utf8(_))
+        ));
+    }
+
+    const RET_CONS_STAT_SERV: &str = concat!(
+        "<retConsStatServ xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+        "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+        "<cStat>107</cStat><xMotivo>Servico em Operacao</xMotivo><cUF>51</cUF>",
+        "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto><tMed>1</tMed>",
+        "</retConsStatServ>"
+    );
+
+    #[test]
+    fn ret_cons_stat_serv() {
+        let ret = RetConsStatServ::from_xml(RET_CONS_STAT_SERV).unwrap();
+        assert!(ret.em_operacao());
+        assert_eq!(ret.c_uf, "51");
+        assert_eq!(ret.t_med, Some(1));
+        assert_eq!(ret.dh_retorno, None);
+
+        let ret = RetConsStatServ::from_xml(
+            &RET_CONS_STAT_SERV
+                .replace("<cStat>107</cStat>", "<cStat>109</cStat>")
+                .replace(
+                    "<tMed>1</tMed>",
+                    "<dhRetorno>2024-01-10T12:00:00-04:00</dhRetorno><xObs>Manutencao</xObs>",
+                ),
+        )
+        .unwrap();
+        assert!(!ret.em_operacao());
+        assert_eq!(ret.t_med, None);
+        assert_eq!(ret.dh_retorno.as_deref(), Some("2024-01-10T12:00:00-04:00"));
+        assert_eq!(ret.x_obs.as_deref(), Some("Manutencao"));
+    }
+
+    #[test]
+    fn ret_cons_stat_serv_invalido() {
+        assert!(matches!(
+            RetConsStatServ::from_xml(&RET_CONS_STAT_SERV.replace("<tMed>1</tMed>", "<tMed>x</tMed>")),
+            Err(RetornoError::ValorInvalido { tag: "tMed", valor }) if valor == "x"
+        ));
+        assert!(matches!(
+            RetConsStatServ::from_xml(&RET_CONS_STAT_SERV.replace("<cUF>51</cUF>", "")),
+            Err(RetornoError::TagNaoEncontrada("cUF"))
+        ));
+        assert!(matches!(
+            RetConsStatServ::from_xml("<retConsSitNFe/>"),
+            Err(RetornoError::TagNaoEncontrada("retConsStatServ"))
+        ));
+        assert!(matches!(
+            RetConsStatServ::from_xml("<retConsStatServ>"),
+            Err(RetornoError::Xml(_))
+        ));
+    }
+
+    fn proc_evento(tp_evento: u32, c_stat: u16) -> String {
+        format!(
+            concat!(
+                "<procEventoNFe versao=\"1.00\">",
+                "<evento versao=\"1.00\"><infEvento Id=\"ID{tp_evento}\">",
+                "<chNFe>51240111222333000181550010000000011000000010</chNFe>",
+                "<dhEvento>2024-01-10T11:00:00-04:00</dhEvento>",
+                "<tpEvento>{tp_evento}</tpEvento><nSeqEvento>1</nSeqEvento>",
+                "</infEvento></evento>",
+                "<retEvento versao=\"1.00\"><infEvento>",
+                "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic><cOrgao>51</cOrgao>",
+                "<cStat>{c_stat}</cStat><xMotivo>Evento</xMotivo>",
+                "</infEvento></retEvento>",
+                "</procEventoNFe>"
+            ),
+            tp_evento = tp_evento,
+            c_stat = c_stat,
+        )
+    }
+
+    fn ret_cons_sit(c_stat: u16, conteudo: &str) -> RetConsSitNFe {
+        RetConsSitNFe::from_xml(&format!(
+            concat!(
+                "<retConsSitNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+                "<tpAmb>2</tpAmb><verAplic>MT_A2RL-4.00</verAplic>",
+                "<cStat>{}</cStat><xMotivo>Situacao</xMotivo><cUF>51</cUF>",
+                "<dhRecbto>2024-01-10T12:00:00-04:00</dhRecbto>",
+                "<chNFe>51240111222333000181550010000000011000000010</chNFe>",
+                "{}",
+                "</retConsSitNFe>"
+            ),
+            c_stat, conteudo
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn ret_cons_sit_nfe() {
+        let ret = ret_cons_sit(100, PROT_NFE);
+        assert_eq!(ret.situacao(), Some(SituacaoNFe::Autorizada));
+        assert_eq!(
+            ret.prot_nfe.unwrap().n_prot.as_deref(),
+            Some("151240000000001")
+        );
+        assert!(ret.proc_evento_nfe.is_empty());
+
+        // Carta de correção não altera a situação da nota.
+        let ret = ret_cons_sit(100, &format!("{}{}", PROT_NFE, proc_evento(110110, 135)));
+        assert_eq!(ret.proc_evento_nfe.len(), 1);
+        assert_eq!(ret.proc_evento_nfe[0].n_seq_evento, Some(1));
+        assert!(ret.cancelamento().is_none());
+        assert_eq!(ret.situacao(), Some(SituacaoNFe::Autorizada));
+
+        assert_eq!(
+            ret_cons_sit(150, "").situacao(),
+            Some(SituacaoNFe::Autorizada)
+        );
+        assert_eq!(
+            ret_cons_sit(110, "").situacao(),
+            Some(SituacaoNFe::Denegada)
+        );
+        assert_eq!(
+            ret_cons_sit(302, "").situacao(),
+            Some(SituacaoNFe::Denegada)
+        );
+        // 217 - NF-e não consta na base de dados da SEFAZ
+        assert_eq!(ret_cons_sit(217, "").situacao(), None);
+    }
+
+    #[test]
+    fn ret_cons_sit_nfe_cancelada() {
+        assert_eq!(
+            ret_cons_sit(101, "").situacao(),
+            Some(SituacaoNFe::Cancelada)
+        );
+        assert_eq!(
+            ret_cons_sit(151, "").situacao(),
+            Some(SituacaoNFe::Cancelada)
+        );
+        for (tp_evento, c_stat) in [(110111, 135), (110111, 155), (110112, 135)] {
+            let ret = ret_cons_sit(
+                100,
+                &format!("{}{}", PROT_NFE, proc_evento(tp_evento, c_stat)),
+            );
+            assert_eq!(
+                ret.cancelamento().map(|proc| proc.tp_evento.as_str()),
+                Some(tp_evento.to_string().as_str())
+            );
+            assert_eq!(ret.situacao(), Some(SituacaoNFe::Cancelada));
+        }
+        // Cancelamento rejeitado não cancela a nota.
+        let ret = ret_cons_sit(100, &format!("{}{}", PROT_NFE, proc_evento(110111, 573)));
+        assert!(ret.cancelamento().is_none());
+        assert_eq!(ret.situacao(), Some(SituacaoNFe::Autorizada));
+    }
+
+    #[test]
+    fn ret_cons_cad() {
+        let xml = concat!(
+            "<retConsCad xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"2.00\"><infCons>",
+            "<verAplic>MT_A2RL-2.00</verAplic><cStat>111</cStat>",
+            "<xMotivo>Consulta cadastro com uma ocorrencia</xMotivo><UF>MT</UF>",
+            "<CNPJ>11222333000181</CNPJ><dhCons>2024-01-10T10:00:00-04:00</dhCons><cUF>51</cUF>",
+            "<infCad><IE>131234567</IE><CNPJ>11222333000181</CNPJ><UF>MT</UF><cSit>1</cSit>",
+            "<indCredNFe>1</indCredNFe><indCredCTe>4</indCredCTe>",
+            "<xNome>EMPRESA MOCK LTDA</xNome><xRegApur>SIMPLES NACIONAL</xRegApur>",
+            "<CNAE>4781400</CNAE><dIniAtiv>2010-01-01</dIniAtiv>",
+            "<ender><xLgr>AVENIDA DAS NACOES</xLgr><nro>100</nro><xBairro>CENTRO</xBairro>",
+            "<cMun>5103403</cMun><xMun>CUIABA</xMun><CEP>78000000</CEP></ender></infCad>",
+            "<infCad><IE>131234568</IE><CNPJ>11222333000181</CNPJ><UF>MT</UF><cSit>0</cSit>",
+            "<indCredNFe>0</indCredNFe><indCredCTe>0</indCredCTe>",
+            "<xNome>EMPRESA MOCK LTDA</xNome><dBaixa>2020-01-01</dBaixa></infCad>",
+            "</infCons></retConsCad>"
+        );
+        let ret = RetConsCad::from_xml(xml).unwrap();
+        assert_eq!(ret.c_stat, 111);
+        assert_eq!(ret.cnpj.as_deref(), Some("11222333000181"));
+        assert_eq!((ret.ie, ret.cpf), (None, None));
+        assert_eq!(ret.inf_cad.len(), 2);
+        let cad = &ret.inf_cad[0];
+        assert_eq!((cad.ie.as_str(), cad.c_sit.as_str()), ("131234567", "1"));
+        assert_eq!(cad.x_reg_apur.as_deref(), Some("SIMPLES NACIONAL"));
+        let ender = cad.ender.as_ref().unwrap();
+        assert_eq!(ender.c_mun.as_deref(), Some("5103403"));
+        assert_eq!(ender.cep.as_deref(), Some("78000000"));
+        assert_eq!(ender.x_cpl, None);
+        let cad = &ret.inf_cad[1];
+        assert_eq!(cad.d_baixa.as_deref(), Some("2020-01-01"));
+        assert!(cad.ender.is_none());
+
+        // Contribuinte sem xNome é rejeitado, assim como a consulta sem infCons.
+        assert!(matches!(
+            RetConsCad::from_xml(&xml.replacen("<xNome>EMPRESA MOCK LTDA</xNome>", "", 1)),
+            Err(RetornoError::TagNaoEncontrada("xNome"))
+        ));
+        assert!(matches!(
+            RetConsCad::from_xml("<retConsCad/>"),
+            Err(RetornoError::TagNaoEncontrada("infCons"))
+        ));
+    }
+}
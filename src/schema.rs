@@ -0,0 +1,457 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    fmt,
+    path::PathBuf,
+    ptr, result,
+    sync::{Arc, Mutex, Once, PoisonError},
+};
+
+use thiserror::Error;
+
+/// Schemas XSD do pacote de liberação da NF-e (PL_009) e de seus eventos.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Schema {
+    Nfe400,
+    EnviNfe400,
+    ConsReciNfe400,
+    ConsSitNfe400,
+    ConsStatServ400,
+    InutNfe400,
+    ConsCad200,
+    EnvEvento100,
+    EnvEventoCancNfe100,
+    EnvCce100,
+    EnvConfRecebto100,
+    DistDfeInt101,
+}
+
+impl Schema {
+    /// Nome do arquivo XSD no pacote de schemas publicado no Portal da NF-e.
+    pub fn arquivo(&self) -> &str {
+        use self::Schema::*;
+        match *self {
+            Nfe400 => "nfe_v4.00.xsd",
+            EnviNfe400 => "enviNFe_v4.00.xsd",
+            ConsReciNfe400 => "consReciNFe_v4.00.xsd",
+            ConsSitNfe400 => "consSitNFe_v4.00.xsd",
+            ConsStatServ400 => "consStatServ_v4.00.xsd",
+            InutNfe400 => "inutNFe_v4.00.xsd",
+            ConsCad200 => "consCad_v2.00.xsd",
+            EnvEvento100 => "envEvento_v1.00.xsd",
+            EnvEventoCancNfe100 => "envEventoCancNFe_v1.00.xsd",
+            EnvCce100 => "envCCe_v1.00.xsd",
+            EnvConfRecebto100 => "envConfRecebto_v1.00.xsd",
+            DistDfeInt101 => "distDFeInt_v1.01.xsd",
+        }
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.arquivo())
+    }
+}
+
+/// Erro de validação localizado no XML ou no schema.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErroSchema {
+    pub linha: u32,
+    pub coluna: u32,
+    pub mensagem: String,
+}
+
+impl fmt::Display for ErroSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.linha, self.coluna, self.mensagem)
+    }
+}
+
+/// Tipo para tratar erros que impedem a validação do XML.
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    /// Arquivo XSD ausente no diretório de schemas ou nos schemas embutidos.
+    #[error("Schema não encontrado: {0}")]
+    SchemaNaoEncontrado(PathBuf),
+    /// Arquivo XSD ilegível ou inválido.
+    #[error("Schema inválido: {schema}")]
+    SchemaInvalido {
+        schema: Schema,
+        erros: Vec<ErroSchema>,
+    },
+    /// XML mal formado.
+    #[error("XML mal formado: {}", .0.first().map(ToString::to_string).unwrap_or_default())]
+    XmlInvalido(Vec<ErroSchema>),
+    /// XML grande demais para ser validado.
+    #[error("XML grande demais para validação")]
+    XmlGrandeDemais,
+}
+
+/// Tipo para tratar retorno da validação, com a lista de erros encontrados (vazia se o XML for
+/// válido).
+pub type SchemaResult = result::Result<Vec<ErroSchema>, SchemaError>;
+
+/// Schemas do pacote de liberação embutidos na biblioteca, gerados a partir de `resources/schemas`.
+#[cfg(feature = "embed_schemas")]
+static EMBUTIDOS: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/schemas.rs"));
+
+/// Prefixo das URLs dos schemas embutidos, que permite à libxml2 resolver os `include` e `import`
+/// relativos entre eles.
+#[cfg(feature = "embed_schemas")]
+const URL_EMBUTIDOS: &str = "fiscalidade:///";
+
+/// Valida XML contra o schema informado usando os schemas embutidos na biblioteca, retornando os
+/// erros com linha e coluna.
+#[cfg(feature = "embed_schemas")]
+pub fn validar_schema(xml: &str, schema: Schema) -> SchemaResult {
+    use std::sync::OnceLock;
+    static SCHEMAS: OnceLock<Schemas> = OnceLock::new();
+    SCHEMAS
+        .get_or_init(Schemas::from_embedded)
+        .validar(xml, schema)
+}
+
+#[derive(Clone, Debug)]
+enum Origem {
+    Diretorio(PathBuf),
+    #[cfg(feature = "embed_schemas")]
+    Embutidos,
+}
+
+/// Validador de XMLs usando os schemas XSD oficiais.
+///
+/// Os arquivos devem ser extraídos do pacote de liberação disponível no Portal da NF-e para um
+/// mesmo diretório, pois os schemas principais incluem `leiauteNFe`, `tiposBasico` e
+/// `xmldsig-core-schema` por caminho relativo. Cada schema é compilado apenas na primeira
+/// validação e reaproveitado pelas seguintes, inclusive entre clones do validador.
+#[derive(Clone, Debug)]
+pub struct Schemas {
+    origem: Origem,
+    compilados: Arc<Mutex<HashMap<Schema, Arc<SchemaXsd>>>>,
+}
+
+impl Schemas {
+    pub fn from_dir<P: Into<PathBuf>>(diretorio: P) -> Self {
+        Self::new(Origem::Diretorio(diretorio.into()))
+    }
+
+    #[cfg(feature = "embed_schemas")]
+    pub fn from_embedded() -> Self {
+        Self::new(Origem::Embutidos)
+    }
+
+    fn new(origem: Origem) -> Self {
+        Self {
+            origem,
+            compilados: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Valida XML contra o schema informado, retornando os erros com linha e coluna.
+    pub fn validar(&self, xml: &str, schema: Schema) -> SchemaResult {
+        let tamanho = c_int::try_from(xml.len()).map_err(|_| SchemaError::XmlGrandeDemais)?;
+        inicializar();
+        let schema_xsd = self.compilado(schema)?;
+        let mut erros = Vec::new();
+        unsafe {
+            let doc = ffi::xmlReadMemory(
+                xml.as_ptr() as *const c_char,
+                tamanho,
+                ptr::null(),
+                ptr::null(),
+                ffi::XML_PARSE_NOERROR | ffi::XML_PARSE_NOWARNING | ffi::XML_PARSE_NONET,
+            );
+            if doc.is_null() {
+                return Err(SchemaError::XmlInvalido(
+                    erro_schema(ffi::xmlGetLastError()).into_iter().collect(),
+                ));
+            }
+            let ctxt = ffi::xmlSchemaNewValidCtxt(schema_xsd.0);
+            ffi::xmlSchemaSetValidStructuredErrors(
+                ctxt,
+                coletar_erro,
+                &mut erros as *mut Vec<ErroSchema> as *mut c_void,
+            );
+            ffi::xmlSchemaValidateDoc(ctxt, doc);
+            ffi::xmlSchemaFreeValidCtxt(ctxt);
+            ffi::xmlFreeDoc(doc);
+        }
+        Ok(erros)
+    }
+
+    fn compilado(&self, schema: Schema) -> result::Result<Arc<SchemaXsd>, SchemaError> {
+        let mut compilados = self
+            .compilados
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(schema_xsd) = compilados.get(&schema) {
+            return Ok(schema_xsd.clone());
+        }
+        let url = match &self.origem {
+            Origem::Diretorio(diretorio) => {
+                let caminho = diretorio.join(schema.arquivo());
+                if !caminho.is_file() {
+                    return Err(SchemaError::SchemaNaoEncontrado(caminho));
+                }
+                caminho.to_string_lossy().into_owned()
+            }
+            #[cfg(feature = "embed_schemas")]
+            Origem::Embutidos => {
+                if embutido(schema.arquivo()).is_none() {
+                    return Err(SchemaError::SchemaNaoEncontrado(schema.arquivo().into()));
+                }
+                format!("{}{}", URL_EMBUTIDOS, schema.arquivo())
+            }
+        };
+        let schema_xsd = Arc::new(
+            SchemaXsd::compilar(&url)
+                .map_err(|erros| SchemaError::SchemaInvalido { schema, erros })?,
+        );
+        compilados.insert(schema, schema_xsd.clone());
+        Ok(schema_xsd)
+    }
+}
+
+#[derive(Debug)]
+struct SchemaXsd(*mut c_void);
+
+// Schemas compilados pela libxml2 são somente leitura e podem ser compartilhados entre threads,
+// desde que cada validação use o seu próprio contexto.
+unsafe impl Send for SchemaXsd {}
+unsafe impl Sync for SchemaXsd {}
+
+impl SchemaXsd {
+    fn compilar(url: &str) -> result::Result<Self, Vec<ErroSchema>> {
+        let url = CString::new(url).map_err(|_| Vec::new())?;
+        let mut erros = Vec::new();
+        let schema = unsafe {
+            let ctxt = ffi::xmlSchemaNewParserCtxt(url.as_ptr());
+            ffi::xmlSchemaSetParserStructuredErrors(
+                ctxt,
+                coletar_erro,
+                &mut erros as *mut Vec<ErroSchema> as *mut c_void,
+            );
+            let schema = ffi::xmlSchemaParse(ctxt);
+            ffi::xmlSchemaFreeParserCtxt(ctxt);
+            schema
+        };
+        if schema.is_null() {
+            return Err(erros);
+        }
+        Ok(Self(schema))
+    }
+}
+
+impl Drop for SchemaXsd {
+    fn drop(&mut self) {
+        unsafe { ffi::xmlSchemaFree(self.0) }
+    }
+}
+
+#[inline]
+fn inicializar() {
+    static INICIALIZACAO: Once = Once::new();
+    // A libxml2 exige inicialização antes do uso por múltiplas threads.
+    INICIALIZACAO.call_once(|| unsafe {
+        ffi::xmlInitParser();
+        #[cfg(feature = "embed_schemas")]
+        ffi::xmlRegisterInputCallbacks(
+            embutido_corresponde,
+            embutido_abrir,
+            embutido_ler,
+            embutido_fechar,
+        );
+    });
+}
+
+#[cfg(feature = "embed_schemas")]
+#[inline]
+fn embutido(url: &str) -> Option<&'static [u8]> {
+    let nome = url.strip_prefix(URL_EMBUTIDOS).unwrap_or(url);
+    EMBUTIDOS
+        .iter()
+        .find(|(arquivo, _)| *arquivo == nome)
+        .map(|(_, conteudo)| *conteudo)
+}
+
+#[cfg(feature = "embed_schemas")]
+#[inline]
+unsafe fn url_embutido(url: *const c_char) -> Option<&'static [u8]> {
+    let url = CStr::from_ptr(url).to_str().ok()?;
+    url.starts_with(URL_EMBUTIDOS)
+        .then(|| embutido(url))
+        .flatten()
+}
+
+#[cfg(feature = "embed_schemas")]
+extern "C" fn embutido_corresponde(url: *const c_char) -> c_int {
+    unsafe { url_embutido(url).is_some().into() }
+}
+
+#[cfg(feature = "embed_schemas")]
+extern "C" fn embutido_abrir(url: *const c_char) -> *mut c_void {
+    match unsafe { url_embutido(url) } {
+        Some(conteudo) => Box::into_raw(Box::new(conteudo)) as *mut c_void,
+        None => ptr::null_mut(),
+    }
+}
+
+#[cfg(feature = "embed_schemas")]
+extern "C" fn embutido_ler(contexto: *mut c_void, buffer: *mut c_char, tamanho: c_int) -> c_int {
+    unsafe {
+        let restante = &mut *(contexto as *mut &[u8]);
+        let lidos = restante.len().min(tamanho.max(0) as usize);
+        ptr::copy_nonoverlapping(restante.as_ptr(), buffer as *mut u8, lidos);
+        *restante = &restante[lidos..];
+        lidos as c_int
+    }
+}
+
+#[cfg(feature = "embed_schemas")]
+extern "C" fn embutido_fechar(contexto: *mut c_void) -> c_int {
+    unsafe { drop(Box::from_raw(contexto as *mut &[u8])) };
+    0
+}
+
+unsafe fn erro_schema(erro: *const ffi::XmlError) -> Option<ErroSchema> {
+    let erro = erro.as_ref()?;
+    let mensagem = if erro.message.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(erro.message)
+            .to_string_lossy()
+            .trim()
+            .to_string()
+    };
+    Some(ErroSchema {
+        linha: erro.line.max(0) as u32,
+        coluna: erro.int2.max(0) as u32,
+        mensagem,
+    })
+}
+
+extern "C" fn coletar_erro(dados: *mut c_void, erro: *const ffi::XmlError) {
+    unsafe {
+        let erros = &mut *(dados as *mut Vec<ErroSchema>);
+        erros.extend(erro_schema(erro));
+    }
+}
+
+mod ffi {
+    use std::ffi::{c_char, c_int, c_void};
+
+    pub const XML_PARSE_NOERROR: c_int = 1 << 5;
+    pub const XML_PARSE_NOWARNING: c_int = 1 << 6;
+    pub const XML_PARSE_NONET: c_int = 1 << 11;
+
+    #[repr(C)]
+    pub struct XmlError {
+        pub domain: c_int,
+        pub code: c_int,
+        pub message: *const c_char,
+        pub level: c_int,
+        pub file: *const c_char,
+        pub line: c_int,
+        pub str1: *const c_char,
+        pub str2: *const c_char,
+        pub str3: *const c_char,
+        pub int1: c_int,
+        pub int2: c_int,
+        pub ctxt: *mut c_void,
+        pub node: *mut c_void,
+    }
+
+    pub type XmlStructuredErrorFunc = extern "C" fn(*mut c_void, *const XmlError);
+    #[cfg(feature = "embed_schemas")]
+    pub type XmlInputMatchCallback = extern "C" fn(*const c_char) -> c_int;
+    #[cfg(feature = "embed_schemas")]
+    pub type XmlInputOpenCallback = extern "C" fn(*const c_char) -> *mut c_void;
+    #[cfg(feature = "embed_schemas")]
+    pub type XmlInputReadCallback = extern "C" fn(*mut c_void, *mut c_char, c_int) -> c_int;
+    #[cfg(feature = "embed_schemas")]
+    pub type XmlInputCloseCallback = extern "C" fn(*mut c_void) -> c_int;
+
+    // A biblioteca é ligada pelo build script, via pkg-config.
+    extern "C" {
+        pub fn xmlInitParser();
+        #[cfg(feature = "embed_schemas")]
+        pub fn xmlRegisterInputCallbacks(
+            match_func: XmlInputMatchCallback,
+            open_func: XmlInputOpenCallback,
+            read_func: XmlInputReadCallback,
+            close_func: XmlInputCloseCallback,
+        ) -> c_int;
+        pub fn xmlGetLastError() -> *const XmlError;
+        pub fn xmlReadMemory(
+            buffer: *const c_char,
+            size: c_int,
+            url: *const c_char,
+            encoding: *const c_char,
+            options: c_int,
+        ) -> *mut c_void;
+        pub fn xmlFreeDoc(doc: *mut c_void);
+        pub fn xmlSchemaNewParserCtxt(url: *const c_char) -> *mut c_void;
+        pub fn xmlSchemaSetParserStructuredErrors(
+            ctxt: *mut c_void,
+            serror: XmlStructuredErrorFunc,
+            ctx: *mut c_void,
+        );
+        pub fn xmlSchemaParse(ctxt: *mut c_void) -> *mut c_void;
+        pub fn xmlSchemaFreeParserCtxt(ctxt: *mut c_void);
+        pub fn xmlSchemaFree(schema: *mut c_void);
+        pub fn xmlSchemaNewValidCtxt(schema: *mut c_void) -> *mut c_void;
+        pub fn xmlSchemaSetValidStructuredErrors(
+            ctxt: *mut c_void,
+            serror: XmlStructuredErrorFunc,
+            ctx: *mut c_void,
+        );
+        pub fn xmlSchemaValidateDoc(ctxt: *mut c_void, doc: *mut c_void) -> c_int;
+        pub fn xmlSchemaFreeValidCtxt(ctxt: *mut c_void);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arquivo() {
+        assert_eq!(Schema::Nfe400.to_string(), "nfe_v4.00.xsd");
+        assert_eq!(Schema::ConsCad200.arquivo(), "consCad_v2.00.xsd");
+        assert_eq!(Schema::DistDfeInt101.arquivo(), "distDFeInt_v1.01.xsd");
+    }
+
+    #[test]
+    fn erro_schema() {
+        let erro = ErroSchema {
+            linha: 2,
+            coluna: 7,
+            mensagem: "Element 'tpAmb': not valid".to_string(),
+        };
+        assert_eq!(erro.to_string(), "2:7: Element 'tpAmb': not valid");
+        assert_eq!(
+            SchemaError::XmlInvalido(vec![erro]).to_string(),
+            "XML mal formado: 2:7: Element 'tpAmb': not valid"
+        );
+        assert_eq!(
+            SchemaError::XmlInvalido(Vec::new()).to_string(),
+            "XML mal formado: "
+        );
+    }
+
+    #[test]
+    fn xml_invalido() {
+        let erros = match Schemas::from_dir(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/schemas"
+        ))
+        .validar("<consStatServ>\n<tpAmb>", Schema::ConsStatServ400)
+        {
+            Err(SchemaError::XmlInvalido(erros)) => erros,
+            resultado => panic!("{:?}", resultado),
+        };
+        assert_eq!(erros.len(), 1);
+        assert_eq!(erros[0].linha, 2);
+        assert!(!erros[0].mensagem.is_empty());
+    }
+}
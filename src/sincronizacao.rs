@@ -0,0 +1,302 @@
+use std::{error::Error, fs::File, io, path::PathBuf, result};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use fs4::fs_std::FileExt;
+use thiserror::Error;
+use tokio::fs::{self, OpenOptions};
+use toml::{de::Error as TomlError, Value as TomlValue};
+
+use crate::{
+    dfe::{Dfe, DfeError},
+    retorno::DocumentoDfe,
+    tipos::{Ambiente, Documento, Uf},
+};
+
+/// Tempo de espera, em segundos, exigido pela SEFAZ após consultar todos os documentos disponíveis.
+pub const ESPERA_SINCRONIZACAO: i64 = 3600;
+
+/// Tipo para tratar erros relacionados a sincronização de NSU.
+#[derive(Error, Debug)]
+pub enum SincronizacaoError {
+    /// Erros relacionados a I/O.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Erros relacionados a TOML.
+    #[error(transparent)]
+    Toml(#[from] TomlError),
+    /// Erros relacionados a consulta de distribuição.
+    #[error(transparent)]
+    Dfe(#[from] DfeError),
+    /// Outro processo já está sincronizando o mesmo documento.
+    #[error("Sincronização já em andamento para {0}")]
+    EmAndamento(String),
+    /// Nova consulta só é permitida após o instante informado, em segundos desde a época Unix.
+    #[error("Nova consulta para {documento} permitida somente após {ate}")]
+    ConsultaBloqueada { documento: String, ate: i64 },
+    /// SEFAZ rejeitou a consulta por consumo indevido (cStat 656).
+    #[error("Consumo indevido para {0}, nova consulta permitida em uma hora")]
+    ConsumoIndevido(String),
+    /// CNPJ/CPF com caracteres não numéricos, impróprio para identificar o estado armazenado.
+    #[error("Documento inválido para sincronização: {0}")]
+    DocumentoInvalido(String),
+    /// Falha ao processar documento recebido, interrompendo a sincronização sem avançar o NSU.
+    #[error("Erro ao processar documento de NSU {nsu}: {erro}")]
+    Processamento {
+        nsu: u64,
+        #[source]
+        erro: Box<dyn Error + Send + Sync>,
+    },
+}
+
+/// Tipo para tratar retorno da sincronização de NSU.
+pub type SincronizacaoResult<T> = result::Result<T, SincronizacaoError>;
+
+/// Estado da sincronização de NSU de um interessado.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EstadoNsu {
+    pub ult_nsu: u64,
+    pub max_nsu: u64,
+    /// Instante, em segundos desde a época Unix, a partir do qual nova consulta é permitida.
+    pub proxima_consulta: i64,
+}
+
+/// Armazenamento do estado de sincronização de NSU por CNPJ/CPF.
+#[async_trait]
+pub trait ArmazenamentoNsu: Send + Sync {
+    /// Bloqueio exclusivo do documento, liberado quando descartado.
+    type Bloqueio: Send;
+
+    /// Obtém acesso exclusivo ao estado do documento, falhando com
+    /// [`SincronizacaoError::EmAndamento`] se outro processo já o possuir.
+    async fn bloquear(&self, documento: &str) -> SincronizacaoResult<Self::Bloqueio>;
+
+    /// Carrega o estado do documento, se existir.
+    async fn carregar(&self, documento: &str) -> SincronizacaoResult<Option<EstadoNsu>>;
+
+    /// Salva o estado do documento.
+    async fn salvar(&self, documento: &str, estado: &EstadoNsu) -> SincronizacaoResult<()>;
+}
+
+/// Armazenamento de estado de NSU em arquivos TOML, um por documento, com bloqueio via arquivo
+/// `.lock` para impedir que dois processos sincronizem o mesmo documento.
+#[derive(Clone, Debug)]
+pub struct ArmazenamentoArquivo {
+    diretorio: PathBuf,
+}
+
+impl ArmazenamentoArquivo {
+    /// Cria novo armazenamento no diretório informado, que é criado se não existir.
+    pub async fn new<P: Into<PathBuf>>(diretorio: P) -> SincronizacaoResult<Self> {
+        let diretorio = diretorio.into();
+        fs::create_dir_all(&diretorio).await?;
+        Ok(Self { diretorio })
+    }
+
+    /// Caminho do arquivo do documento, aceitando apenas dígitos para que o nome não escape do
+    /// diretório do armazenamento.
+    #[inline]
+    fn caminho(&self, documento: &str, extensao: &str) -> SincronizacaoResult<PathBuf> {
+        if documento.is_empty() || !documento.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(SincronizacaoError::DocumentoInvalido(documento.to_string()));
+        }
+        Ok(self.diretorio.join(format!("{}.{}", documento, extensao)))
+    }
+}
+
+#[async_trait]
+impl ArmazenamentoNsu for ArmazenamentoArquivo {
+    type Bloqueio = File;
+
+    async fn bloquear(&self, documento: &str) -> SincronizacaoResult<File> {
+        let arquivo = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.caminho(documento, "lock")?)
+            .await?
+            .into_std()
+            .await;
+        // Apenas tenta obter o bloqueio, sem aguardar, então não bloqueia o runtime.
+        if !FileExt::try_lock_exclusive(&arquivo)? {
+            return Err(SincronizacaoError::EmAndamento(documento.to_string()));
+        }
+        Ok(arquivo)
+    }
+
+    async fn carregar(&self, documento: &str) -> SincronizacaoResult<Option<EstadoNsu>> {
+        let conteudo = match fs::read_to_string(self.caminho(documento, "toml")?).await {
+            Ok(conteudo) => conteudo,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let valor: TomlValue = toml::from_str(&conteudo)?;
+        let inteiro = |chave| valor.get(chave).and_then(TomlValue::as_integer);
+        Ok(Some(EstadoNsu {
+            ult_nsu: inteiro("ult_nsu").unwrap_or_default() as u64,
+            max_nsu: inteiro("max_nsu").unwrap_or_default() as u64,
+            proxima_consulta: inteiro("proxima_consulta").unwrap_or_default(),
+        }))
+    }
+
+    async fn salvar(&self, documento: &str, estado: &EstadoNsu) -> SincronizacaoResult<()> {
+        let conteudo = format!(
+            "ult_nsu = {}\nmax_nsu = {}\nproxima_consulta = {}\n",
+            estado.ult_nsu, estado.max_nsu, estado.proxima_consulta
+        );
+        // Grava em arquivo temporário e renomeia para não corromper o estado em caso de falha.
+        let temporario = self.caminho(documento, "toml.tmp")?;
+        fs::write(&temporario, conteudo).await?;
+        fs::rename(temporario, self.caminho(documento, "toml")?).await?;
+        Ok(())
+    }
+}
+
+/// Sincronizador de documentos da distribuição de DF-e.
+///
+/// Consulta os documentos a partir do último NSU armazenado até alcançar o maior NSU informado
+/// pela SEFAZ e então aguarda uma hora antes de permitir nova consulta, evitando a rejeição por
+/// consumo indevido (cStat 656).
+pub struct Sincronizador<A: ArmazenamentoNsu = ArmazenamentoArquivo> {
+    dfe: Dfe,
+    armazenamento: A,
+    uf: Uf,
+    ambiente: Ambiente,
+}
+
+impl<A: ArmazenamentoNsu> Sincronizador<A> {
+    /// Cria novo sincronizador para a UF do interessado.
+    pub fn new(dfe: Dfe, armazenamento: A, uf: Uf, ambiente: Ambiente) -> Self {
+        Self {
+            dfe,
+            armazenamento,
+            uf,
+            ambiente,
+        }
+    }
+
+    /// Sincroniza documentos do interessado, chamando `processar` para cada documento recebido
+    /// antes de armazenar o novo NSU. Se `processar` falhar, a sincronização é interrompida sem
+    /// armazenar o NSU do lote, que é consultado novamente na próxima sincronização.
+    pub async fn sincronizar<F>(
+        &self,
+        documento: Documento<'_>,
+        mut processar: F,
+    ) -> SincronizacaoResult<EstadoNsu>
+    where
+        F: FnMut(DocumentoDfe) -> result::Result<(), Box<dyn Error + Send + Sync>>,
+    {
+        let chave = documento.as_str();
+        let _bloqueio = self.armazenamento.bloquear(chave).await?;
+        let mut estado = self
+            .armazenamento
+            .carregar(chave)
+            .await?
+            .unwrap_or_default();
+        if estado.proxima_consulta > Utc::now().timestamp() {
+            return Err(SincronizacaoError::ConsultaBloqueada {
+                documento: chave.to_string(),
+                ate: estado.proxima_consulta,
+            });
+        }
+        loop {
+            let ret = match self
+                .dfe
+                .clone()
+                .distribuicao_por_ultimo_nsu(
+                    self.uf,
+                    self.ambiente,
+                    documento.clone(),
+                    estado.ult_nsu,
+                )
+                .await
+            {
+                Ok(ret) => ret,
+                // 656 - Consumo indevido
+                Err(DfeError::Rejeicao { c_stat: 656, .. }) => {
+                    estado.proxima_consulta = Utc::now().timestamp() + ESPERA_SINCRONIZACAO;
+                    self.armazenamento.salvar(chave, &estado).await?;
+                    return Err(SincronizacaoError::ConsumoIndevido(chave.to_string()));
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let ult_nsu = estado.ult_nsu;
+            if ret.ult_nsu > 0 {
+                estado.ult_nsu = ret.ult_nsu;
+                estado.max_nsu = ret.max_nsu;
+            }
+            for documento in ret.documentos {
+                let nsu = documento.nsu;
+                processar(documento)
+                    .map_err(|erro| SincronizacaoError::Processamento { nsu, erro })?;
+            }
+            // Sem avanço de NSU não há mais documentos a consultar por ora.
+            if estado.ult_nsu >= estado.max_nsu || estado.ult_nsu <= ult_nsu {
+                estado.proxima_consulta = Utc::now().timestamp() + ESPERA_SINCRONIZACAO;
+                self.armazenamento.salvar(chave, &estado).await?;
+                return Ok(estado);
+            }
+            self.armazenamento.salvar(chave, &estado).await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    async fn armazenamento(nome: &str) -> ArmazenamentoArquivo {
+        let diretorio = env::temp_dir().join(format!("fiscalidade-{}-{}", nome, process::id()));
+        let _ = fs::remove_dir_all(&diretorio).await;
+        ArmazenamentoArquivo::new(diretorio).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn caminho() {
+        let armazenamento = armazenamento("caminho").await;
+        assert_eq!(
+            armazenamento.caminho("00000000000191", "toml").unwrap(),
+            armazenamento.diretorio.join("00000000000191.toml")
+        );
+        for documento in ["", "..", "0000/0191", "00000000000191 "] {
+            assert!(matches!(
+                armazenamento.caminho(documento, "toml"),
+                Err(SincronizacaoError::DocumentoInvalido(d)) if d == documento
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn estado() {
+        let armazenamento = armazenamento("estado").await;
+        assert_eq!(armazenamento.carregar("52998224725").await.unwrap(), None);
+        let estado = EstadoNsu {
+            ult_nsu: 10,
+            max_nsu: 20,
+            proxima_consulta: 1_700_000_000,
+        };
+        armazenamento.salvar("52998224725", &estado).await.unwrap();
+        assert_eq!(
+            armazenamento.carregar("52998224725").await.unwrap(),
+            Some(estado)
+        );
+        assert!(!armazenamento
+            .caminho("52998224725", "toml.tmp")
+            .unwrap()
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn bloqueio() {
+        let armazenamento = armazenamento("bloqueio").await;
+        let bloqueio = armazenamento.bloquear("52998224725").await.unwrap();
+        assert!(matches!(
+            armazenamento.bloquear("52998224725").await,
+            Err(SincronizacaoError::EmAndamento(d)) if d == "52998224725"
+        ));
+        drop(bloqueio);
+        assert!(armazenamento.bloquear("52998224725").await.is_ok());
+    }
+}
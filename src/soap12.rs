@@ -9,6 +9,14 @@ pub fn format_action(operacao: &str) -> String {
     )
 }
 
+#[inline]
+pub fn format_action_dist_dfe(operacao: &str) -> String {
+    format!(
+        "{action}/nfeDistDFeInteresse",
+        action = format_action(operacao)
+    )
+}
+
 #[inline]
 pub fn format_dados_msg(dados: &str, operacao: &str) -> String {
     format!(
@@ -124,3 +132,637 @@ pub fn format_cons_cad(cuf: u8, versao: &str, operacao: &str, doc: &str, doc_tag
         operacao,
     )
 }
+
+#[inline]
+fn remover_declaracao(xml: &str) -> &str {
+    let xml = xml.trim();
+    match xml.strip_prefix("<?xml") {
+        Some(resto) => resto
+            .find("?>")
+            .map_or(xml, |pos| resto[pos + 2..].trim_start()),
+        None => xml,
+    }
+}
+
+#[inline]
+pub fn format_envi_nfe(
+    versao: &str,
+    operacao: &str,
+    id_lote: &str,
+    ind_sinc: u8,
+    nfes: &[&str],
+) -> String {
+    format_dados_msg(
+        format!(
+            concat!(
+                "<enviNFe xmlns=\"{portal}\" versao=\"{versao}\">",
+                "<idLote>{id_lote}</idLote>",
+                "<indSinc>{ind_sinc}</indSinc>",
+                "{nfes}",
+                "</enviNFe>"
+            ),
+            portal = PORTAL_FISCAL,
+            versao = versao,
+            id_lote = id_lote,
+            ind_sinc = ind_sinc,
+            nfes = nfes
+                .iter()
+                .map(|nfe| remover_declaracao(nfe))
+                .collect::<String>(),
+        )
+        .as_str(),
+        operacao,
+    )
+}
+
+const VERSAO_EVENTO: &str = "1.00";
+
+const CONDICAO_USO_CCE: &str = concat!(
+    "A Carta de Correcao e disciplinada pelo paragrafo 1o-A do art. 7o do Convenio S/N, ",
+    "de 15 de dezembro de 1970 e pode ser utilizada para regularizacao de erro ocorrido ",
+    "na emissao de documento fiscal, desde que o erro nao esteja relacionado com: ",
+    "I - as variaveis que determinam o valor do imposto tais como: base de calculo, ",
+    "aliquota, diferenca de preco, quantidade, valor da operacao ou da prestacao; ",
+    "II - a correcao de dados cadastrais que implique mudanca do remetente ou do destinatario; ",
+    "III - a data de emissao ou de saida."
+);
+
+#[inline]
+pub fn escapar(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[inline]
+pub fn format_id_evento(tp_evento: u32, ch: &str, n_seq: u8) -> String {
+    format!(
+        "ID{tp_evento}{ch}{n_seq:02}",
+        tp_evento = tp_evento,
+        ch = ch,
+        n_seq = n_seq
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+#[inline]
+pub fn format_evento(
+    id: &str,
+    c_orgao: u8,
+    tp_amb: u8,
+    doc: &str,
+    doc_tag: &str,
+    ch: &str,
+    dh_evento: &str,
+    tp_evento: u32,
+    n_seq: u8,
+    det: &str,
+) -> String {
+    format!(
+        concat!(
+            "<evento xmlns=\"{portal}\" versao=\"{versao}\">",
+            "<infEvento Id=\"{id}\">",
+            "<cOrgao>{c_orgao}</cOrgao>",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<{doc_tag_prefixo}>{doc}</{doc_tag_sufixo}>",
+            "<chNFe>{ch}</chNFe>",
+            "<dhEvento>{dh_evento}</dhEvento>",
+            "<tpEvento>{tp_evento}</tpEvento>",
+            "<nSeqEvento>{n_seq}</nSeqEvento>",
+            "<verEvento>{versao}</verEvento>",
+            "<detEvento versao=\"{versao}\">{det}</detEvento>",
+            "</infEvento>",
+            "</evento>"
+        ),
+        portal = PORTAL_FISCAL,
+        versao = VERSAO_EVENTO,
+        id = id,
+        c_orgao = c_orgao,
+        tp_amb = tp_amb,
+        doc_tag_prefixo = doc_tag,
+        doc = doc,
+        doc_tag_sufixo = doc_tag,
+        ch = ch,
+        dh_evento = dh_evento,
+        tp_evento = tp_evento,
+        n_seq = n_seq,
+        det = det,
+    )
+}
+
+#[inline]
+pub fn format_det_cancelamento(desc_evento: &str, n_prot: &str, x_just: &str) -> String {
+    format!(
+        concat!(
+            "<descEvento>{desc_evento}</descEvento>",
+            "<nProt>{n_prot}</nProt>",
+            "<xJust>{x_just}</xJust>"
+        ),
+        desc_evento = desc_evento,
+        n_prot = n_prot,
+        x_just = escapar(x_just.trim()),
+    )
+}
+
+#[inline]
+pub fn format_det_cancelamento_substituicao(
+    desc_evento: &str,
+    c_orgao_autor: u8,
+    ver_aplic: &str,
+    n_prot: &str,
+    x_just: &str,
+    ch_ref: &str,
+) -> String {
+    format!(
+        concat!(
+            "<descEvento>{desc_evento}</descEvento>",
+            "<cOrgaoAutor>{c_orgao_autor}</cOrgaoAutor>",
+            "<tpAutor>1</tpAutor>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<nProt>{n_prot}</nProt>",
+            "<xJust>{x_just}</xJust>",
+            "<chNFeRef>{ch_ref}</chNFeRef>"
+        ),
+        desc_evento = desc_evento,
+        c_orgao_autor = c_orgao_autor,
+        ver_aplic = ver_aplic,
+        n_prot = n_prot,
+        x_just = escapar(x_just.trim()),
+        ch_ref = ch_ref,
+    )
+}
+
+#[inline]
+pub fn format_det_carta_correcao(desc_evento: &str, x_correcao: &str) -> String {
+    format!(
+        concat!(
+            "<descEvento>{desc_evento}</descEvento>",
+            "<xCorrecao>{x_correcao}</xCorrecao>",
+            "<xCondUso>{x_cond_uso}</xCondUso>"
+        ),
+        desc_evento = desc_evento,
+        x_correcao = escapar(x_correcao.trim()),
+        x_cond_uso = CONDICAO_USO_CCE,
+    )
+}
+
+#[inline]
+pub fn format_det_manifestacao(desc_evento: &str, x_just: Option<&str>) -> String {
+    format!(
+        "<descEvento>{desc_evento}</descEvento>{x_just}",
+        desc_evento = desc_evento,
+        x_just = x_just
+            .map(|x_just| format!("<xJust>{}</xJust>", escapar(x_just.trim())))
+            .unwrap_or_default(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+#[inline]
+pub fn format_det_epec(
+    desc_evento: &str,
+    c_orgao_autor: u8,
+    ver_aplic: &str,
+    dh_emi: &str,
+    tp_nf: &str,
+    ie: &str,
+    dest_uf: &str,
+    dest_doc: &str,
+    dest_doc_tag: &str,
+    dest_ie: Option<&str>,
+    v_nf: &str,
+    v_icms: &str,
+    v_st: &str,
+) -> String {
+    format!(
+        concat!(
+            "<descEvento>{desc_evento}</descEvento>",
+            "<cOrgaoAutor>{c_orgao_autor}</cOrgaoAutor>",
+            "<tpAutor>1</tpAutor>",
+            "<verAplic>{ver_aplic}</verAplic>",
+            "<dhEmi>{dh_emi}</dhEmi>",
+            "<tpNF>{tp_nf}</tpNF>",
+            "<IE>{ie}</IE>",
+            "<dest>",
+            "<UF>{dest_uf}</UF>",
+            "<{dest_doc_tag_prefixo}>{dest_doc}</{dest_doc_tag_sufixo}>",
+            "{dest_ie}",
+            "<vNF>{v_nf}</vNF>",
+            "<vICMS>{v_icms}</vICMS>",
+            "<vST>{v_st}</vST>",
+            "</dest>"
+        ),
+        desc_evento = desc_evento,
+        c_orgao_autor = c_orgao_autor,
+        ver_aplic = ver_aplic,
+        dh_emi = dh_emi,
+        tp_nf = tp_nf,
+        ie = ie,
+        dest_uf = dest_uf,
+        dest_doc_tag_prefixo = dest_doc_tag,
+        dest_doc = escapar(dest_doc),
+        dest_doc_tag_sufixo = dest_doc_tag,
+        dest_ie = dest_ie
+            .map(|ie| format!("<IE>{}</IE>", ie))
+            .unwrap_or_default(),
+        v_nf = v_nf,
+        v_icms = v_icms,
+        v_st = v_st,
+    )
+}
+
+#[inline]
+pub fn format_env_evento(operacao: &str, id_lote: &str, eventos: &[String]) -> String {
+    format_dados_msg(
+        format!(
+            concat!(
+                "<envEvento xmlns=\"{portal}\" versao=\"{versao}\">",
+                "<idLote>{id_lote}</idLote>",
+                "{eventos}",
+                "</envEvento>"
+            ),
+            portal = PORTAL_FISCAL,
+            versao = VERSAO_EVENTO,
+            id_lote = id_lote,
+            eventos = eventos
+                .iter()
+                .map(|evento| remover_declaracao(evento))
+                .collect::<String>(),
+        )
+        .as_str(),
+        operacao,
+    )
+}
+
+#[inline]
+pub fn format_id_inutilizacao(
+    cuf: u8,
+    ano: u16,
+    cnpj: &str,
+    modelo: u8,
+    serie: u16,
+    n_ini: u32,
+    n_fin: u32,
+) -> String {
+    format!(
+        "ID{cuf:02}{ano:02}{cnpj}{modelo:02}{serie:03}{n_ini:09}{n_fin:09}",
+        cuf = cuf,
+        ano = ano,
+        cnpj = cnpj,
+        modelo = modelo,
+        serie = serie,
+        n_ini = n_ini,
+        n_fin = n_fin,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+#[inline]
+pub fn format_inut_nfe(
+    versao: &str,
+    id: &str,
+    tp_amb: u8,
+    cuf: u8,
+    ano: u16,
+    cnpj: &str,
+    modelo: u8,
+    serie: u16,
+    n_ini: u32,
+    n_fin: u32,
+    x_just: &str,
+) -> String {
+    format!(
+        concat!(
+            "<inutNFe xmlns=\"{portal}\" versao=\"{versao}\">",
+            "<infInut Id=\"{id}\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<xServ>INUTILIZAR</xServ>",
+            "<cUF>{cuf}</cUF>",
+            "<ano>{ano:02}</ano>",
+            "<CNPJ>{cnpj}</CNPJ>",
+            "<mod>{modelo}</mod>",
+            "<serie>{serie}</serie>",
+            "<nNFIni>{n_ini}</nNFIni>",
+            "<nNFFin>{n_fin}</nNFFin>",
+            "<xJust>{x_just}</xJust>",
+            "</infInut>",
+            "</inutNFe>"
+        ),
+        portal = PORTAL_FISCAL,
+        versao = versao,
+        id = id,
+        tp_amb = tp_amb,
+        cuf = cuf,
+        ano = ano,
+        cnpj = cnpj,
+        modelo = modelo,
+        serie = serie,
+        n_ini = n_ini,
+        n_fin = n_fin,
+        x_just = escapar(x_just.trim()),
+    )
+}
+
+#[inline]
+pub fn format_dist_nsu(ult_nsu: u64) -> String {
+    format!(
+        "<distNSU><ultNSU>{ult_nsu:015}</ultNSU></distNSU>",
+        ult_nsu = ult_nsu
+    )
+}
+
+#[inline]
+pub fn format_cons_nsu(nsu: u64) -> String {
+    format!("<consNSU><NSU>{nsu:015}</NSU></consNSU>", nsu = nsu)
+}
+
+#[inline]
+pub fn format_cons_ch_nfe(ch: &str) -> String {
+    format!("<consChNFe><chNFe>{ch}</chNFe></consChNFe>", ch = ch)
+}
+
+#[inline]
+pub fn format_dist_dfe_int(
+    cuf: u8,
+    tp_amb: u8,
+    versao: &str,
+    operacao: &str,
+    doc: &str,
+    doc_tag: &str,
+    consulta: &str,
+) -> String {
+    // Diferente dos demais serviços, a mensagem da distribuição é envolvida pela operação
+    // "nfeDistDFeInteresse".
+    format!(
+        concat!(
+            "<nfeDistDFeInteresse xmlns=\"{namespace}\">",
+            "<nfeDadosMsg>",
+            "<distDFeInt xmlns=\"{portal}\" versao=\"{versao}\">",
+            "<tpAmb>{tp_amb}</tpAmb>",
+            "<cUFAutor>{cuf}</cUFAutor>",
+            "<{doc_tag_prefixo}>{doc}</{doc_tag_sufixo}>",
+            "{consulta}",
+            "</distDFeInt>",
+            "</nfeDadosMsg>",
+            "</nfeDistDFeInteresse>"
+        ),
+        namespace = format_action(operacao),
+        portal = PORTAL_FISCAL,
+        versao = versao,
+        tp_amb = tp_amb,
+        cuf = cuf,
+        doc_tag_prefixo = doc_tag,
+        doc = doc,
+        doc_tag_sufixo = doc_tag,
+        consulta = consulta,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envi_nfe_sem_declaracao() {
+        let xml = format_envi_nfe(
+            "4.00",
+            "NFeAutorizacao4",
+            "1",
+            1,
+            &[
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<NFe/>",
+                "<NFe/>",
+            ],
+        );
+        assert!(xml.contains(concat!(
+            "<enviNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+            "<idLote>1</idLote><indSinc>1</indSinc><NFe/><NFe/></enviNFe>"
+        )));
+        assert!(xml.starts_with(
+            "<nfeDadosMsg xmlns=\"http://www.portalfiscal.inf.br/nfe/wsdl/NFeAutorizacao4\">"
+        ));
+        assert!(!xml.contains("<?xml"));
+    }
+
+    #[test]
+    fn cons_reci() {
+        assert_eq!(
+            format_cons_reci(2, "4.00", "NFeRetAutorizacao4", "510000000000001"),
+            concat!(
+                "<nfeDadosMsg xmlns=\"http://www.portalfiscal.inf.br/nfe/wsdl/NFeRetAutorizacao4\">",
+                "<consReciNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+                "<tpAmb>2</tpAmb><nRec>510000000000001</nRec>",
+                "</consReciNFe></nfeDadosMsg>"
+            )
+        );
+    }
+
+    #[test]
+    fn id_evento() {
+        assert_eq!(
+            format_id_evento(110111, "51240111222333000181550010000000011000000010", 1),
+            "ID1101115124011122233300018155001000000001100000001001"
+        );
+    }
+
+    #[test]
+    fn det_cancelamento() {
+        assert_eq!(
+            format_det_cancelamento(
+                "Cancelamento",
+                "151240000000001",
+                " Erro de <digitação> & \"preço\" "
+            ),
+            concat!(
+                "<descEvento>Cancelamento</descEvento>",
+                "<nProt>151240000000001</nProt>",
+                "<xJust>Erro de &lt;digitação&gt; &amp; &quot;preço&quot;</xJust>"
+            )
+        );
+    }
+
+    #[test]
+    fn det_carta_correcao() {
+        let det = format_det_carta_correcao("Carta de Correcao", " Endereço: Rua A & B ");
+        assert!(det.starts_with(concat!(
+            "<descEvento>Carta de Correcao</descEvento>",
+            "<xCorrecao>Endereço: Rua A &amp; B</xCorrecao>",
+            "<xCondUso>A Carta de Correcao e disciplinada"
+        )));
+        assert!(det.ends_with("III - a data de emissao ou de saida.</xCondUso>"));
+    }
+
+    #[test]
+    fn inut_nfe() {
+        let id = format_id_inutilizacao(51, 24, "11222333000181", 55, 1, 10, 20);
+        assert_eq!(id, "ID51241122233300018155001000000010000000020");
+        assert_eq!(
+            format_inut_nfe(
+                "4.00",
+                &id,
+                2,
+                51,
+                24,
+                "11222333000181",
+                55,
+                1,
+                10,
+                20,
+                " Numeração pulada <erro> "
+            ),
+            concat!(
+                "<inutNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+                "<infInut Id=\"ID51241122233300018155001000000010000000020\">",
+                "<tpAmb>2</tpAmb><xServ>INUTILIZAR</xServ><cUF>51</cUF><ano>24</ano>",
+                "<CNPJ>11222333000181</CNPJ><mod>55</mod><serie>1</serie>",
+                "<nNFIni>10</nNFIni><nNFFin>20</nNFFin>",
+                "<xJust>Numeração pulada &lt;erro&gt;</xJust>",
+                "</infInut></inutNFe>"
+            )
+        );
+    }
+
+    #[test]
+    fn det_manifestacao() {
+        assert_eq!(
+            format_det_manifestacao("Ciencia da Operacao", None),
+            "<descEvento>Ciencia da Operacao</descEvento>"
+        );
+        assert_eq!(
+            format_det_manifestacao("Operacao nao Realizada", Some(" Devolução & troca ")),
+            concat!(
+                "<descEvento>Operacao nao Realizada</descEvento>",
+                "<xJust>Devolução &amp; troca</xJust>"
+            )
+        );
+    }
+
+    #[test]
+    fn det_epec() {
+        let det = format_det_epec(
+            "EPEC",
+            51,
+            "fiscalidade",
+            "2024-01-10T10:00:00-04:00",
+            "1",
+            "131234565",
+            "EX",
+            "A&B",
+            "idEstrangeiro",
+            None,
+            "100.00",
+            "18.00",
+            "0.00",
+        );
+        assert_eq!(
+            det,
+            concat!(
+                "<descEvento>EPEC</descEvento><cOrgaoAutor>51</cOrgaoAutor><tpAutor>1</tpAutor>",
+                "<verAplic>fiscalidade</verAplic><dhEmi>2024-01-10T10:00:00-04:00</dhEmi>",
+                "<tpNF>1</tpNF><IE>131234565</IE><dest><UF>EX</UF>",
+                "<idEstrangeiro>A&amp;B</idEstrangeiro><vNF>100.00</vNF><vICMS>18.00</vICMS>",
+                "<vST>0.00</vST></dest>"
+            )
+        );
+        assert!(format_det_epec(
+            "EPEC",
+            51,
+            "",
+            "",
+            "1",
+            "",
+            "SP",
+            "1",
+            "CNPJ",
+            Some("123"),
+            "",
+            "",
+            ""
+        )
+        .contains("<CNPJ>1</CNPJ><IE>123</IE>"));
+    }
+
+    #[test]
+    fn det_cancelamento_substituicao() {
+        let det = format_det_cancelamento_substituicao(
+            "Cancelamento por substituicao",
+            51,
+            "fiscalidade",
+            "151240000000001",
+            " Troca de NFC-e <substituida> ",
+            "51240111222333000181650010000000021000000020",
+        );
+        assert_eq!(
+            det,
+            concat!(
+                "<descEvento>Cancelamento por substituicao</descEvento>",
+                "<cOrgaoAutor>51</cOrgaoAutor><tpAutor>1</tpAutor>",
+                "<verAplic>fiscalidade</verAplic><nProt>151240000000001</nProt>",
+                "<xJust>Troca de NFC-e &lt;substituida&gt;</xJust>",
+                "<chNFeRef>51240111222333000181650010000000021000000020</chNFeRef>"
+            )
+        );
+    }
+
+    #[test]
+    fn dist_dfe_int() {
+        assert_eq!(
+            format_dist_nsu(12),
+            "<distNSU><ultNSU>000000000000012</ultNSU></distNSU>"
+        );
+        assert_eq!(
+            format_cons_nsu(7),
+            "<consNSU><NSU>000000000000007</NSU></consNSU>"
+        );
+        let xml = format_dist_dfe_int(
+            51,
+            2,
+            "1.01",
+            "NFeDistribuicaoDFe",
+            "52998224725",
+            "CPF",
+            &format_cons_ch_nfe("51240111222333000181550010000000011000000010"),
+        );
+        assert!(xml.starts_with(concat!(
+            "<nfeDistDFeInteresse xmlns=\"http://www.portalfiscal.inf.br/nfe/wsdl/NFeDistribuicaoDFe\">",
+            "<nfeDadosMsg><distDFeInt xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"1.01\">",
+            "<tpAmb>2</tpAmb><cUFAutor>51</cUFAutor><CPF>52998224725</CPF>",
+            "<consChNFe><chNFe>51240111222333000181550010000000011000000010</chNFe></consChNFe>",
+        )));
+    }
+
+    #[test]
+    fn cons_stat_serv() {
+        assert_eq!(
+            format_cons_stat_serv(51, 2, "4.00", "NFeStatusServico4"),
+            concat!(
+                "<nfeDadosMsg xmlns=\"http://www.portalfiscal.inf.br/nfe/wsdl/NFeStatusServico4\">",
+                "<consStatServ xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+                "<tpAmb>2</tpAmb><cUF>51</cUF><xServ>STATUS</xServ>",
+                "</consStatServ></nfeDadosMsg>"
+            )
+        );
+    }
+
+    #[test]
+    fn cons_sit() {
+        assert_eq!(
+            format_cons_sit(
+                1,
+                "4.00",
+                "NFeConsultaProtocolo4",
+                "51240111222333000181550010000000011000000010"
+            ),
+            concat!(
+                "<nfeDadosMsg xmlns=\"http://www.portalfiscal.inf.br/nfe/wsdl/NFeConsultaProtocolo4\">",
+                "<consSitNFe xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+                "<tpAmb>1</tpAmb><xServ>CONSULTAR</xServ>",
+                "<chNFe>51240111222333000181550010000000011000000010</chNFe>",
+                "</consSitNFe></nfeDadosMsg>"
+            )
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt;
+
+use self::ClassificacaoStatus::*;
+
+/// Classificação de um código de status (cStat) retornado pela SEFAZ.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ClassificacaoStatus {
+    /// Pedido processado com sucesso.
+    Sucesso,
+    /// Uso da NF-e denegado, a numeração não pode ser reutilizada.
+    Denegacao,
+    /// Falha temporária, o pedido pode ser repetido mais tarde.
+    Transitoria,
+    /// Rejeição que exige correção do pedido antes de novo envio.
+    Rejeicao,
+}
+
+/// Código de status (cStat) do Manual de Orientação do Contribuinte (MOC 7.0).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CodigoStatus {
+    pub codigo: u16,
+    pub descricao: &'static str,
+    pub classificacao: ClassificacaoStatus,
+}
+
+/// Catálogo parcial com os códigos de status mais frequentes do MOC 7.0, ordenados por código para
+/// busca binária. Não cobre todas as regras de validação das faixas 2xx a 9xx; códigos ausentes são
+/// classificados como rejeição por [`CodigoStatus::classificar`].
+const CODIGOS: &[(u16, &str, ClassificacaoStatus)] = &[
+    (100, "Autorizado o uso da NF-e", Sucesso),
+    (101, "Cancelamento de NF-e homologado", Sucesso),
+    (102, "Inutilização de número homologado", Sucesso),
+    (103, "Lote recebido com sucesso", Sucesso),
+    (104, "Lote processado", Sucesso),
+    (105, "Lote em processamento", Transitoria),
+    (106, "Lote não localizado", Rejeicao),
+    (107, "Serviço em Operação", Sucesso),
+    (108, "Serviço Paralisado Momentaneamente (curto prazo)", Transitoria),
+    (109, "Serviço Paralisado sem Previsão", Transitoria),
+    (110, "Uso Denegado", Denegacao),
+    (111, "Consulta cadastro com uma ocorrência", Sucesso),
+    (112, "Consulta cadastro com mais de uma ocorrência", Sucesso),
+    (113, "Serviço SVC em operação. Desativação prevista para a UF em dd/mm/aa, às hh:mm horas", Sucesso),
+    (114, "SVC-[SP/RS] desabilitada pela SEFAZ de Origem", Rejeicao),
+    (124, "EPEC Autorizado", Sucesso),
+    (128, "Lote de Evento Processado", Sucesso),
+    (135, "Evento registrado e vinculado a NF-e", Sucesso),
+    (136, "Evento registrado, mas não vinculado a NF-e", Sucesso),
+    (137, "Nenhum documento localizado para o Destinatário", Sucesso),
+    (138, "Documento localizado para o Destinatário", Sucesso),
+    (139, "Pedido de Download processado", Sucesso),
+    (140, "Download disponibilizado", Sucesso),
+    (142, "Ambiente de Contingência EPEC bloqueado para o Emitente", Rejeicao),
+    (150, "Autorizado o uso da NF-e, autorização fora de prazo", Sucesso),
+    (151, "Cancelamento de NF-e homologado fora de prazo", Sucesso),
+    (155, "Cancelamento homologado fora de prazo", Sucesso),
+    (201, "Rejeição: Número máximo de numeração a inutilizar ultrapassou o limite", Rejeicao),
+    (202, "Rejeição: Falha no reconhecimento da autoria ou integridade do arquivo digital", Rejeicao),
+    (203, "Rejeição: Emissor não habilitado para emissão da NF-e", Rejeicao),
+    (204, "Rejeição: Duplicidade de NF-e", Rejeicao),
+    (205, "Rejeição: NF-e está denegada na base de dados da SEFAZ", Rejeicao),
+    (206, "Rejeição: NF-e já está inutilizada na Base de dados da SEFAZ", Rejeicao),
+    (207, "Rejeição: CNPJ do emitente inválido", Rejeicao),
+    (208, "Rejeição: CNPJ do destinatário inválido", Rejeicao),
+    (209, "Rejeição: IE do emitente inválida", Rejeicao),
+    (210, "Rejeição: IE do destinatário inválida", Rejeicao),
+    (211, "Rejeição: IE do substituto inválida", Rejeicao),
+    (212, "Rejeição: Data de emissão NF-e posterior a data de recebimento", Rejeicao),
+    (213, "Rejeição: CNPJ-Base do Emitente difere do CNPJ-Base do Certificado Digital", Rejeicao),
+    (214, "Rejeição: Tamanho da mensagem excedeu o limite estabelecido", Rejeicao),
+    (215, "Rejeição: Falha no schema XML", Rejeicao),
+    (216, "Rejeição: Chave de Acesso difere da cadastrada", Rejeicao),
+    (217, "Rejeição: NF-e não consta na base de dados da SEFAZ", Rejeicao),
+    (218, "Rejeição: NF-e já está cancelada na base de dados da SEFAZ", Rejeicao),
+    (219, "Rejeição: Circulação da NF-e verificada", Rejeicao),
+    (220, "Rejeição: Prazo de Cancelamento superior ao previsto na Legislação", Rejeicao),
+    (221, "Rejeição: Confirmado o recebimento da NF-e pelo destinatário", Rejeicao),
+    (222, "Rejeição: Protocolo de Autorização de Uso difere do cadastrado", Rejeicao),
+    (223, "Rejeição: CNPJ do transmissor do lote difere do CNPJ do transmissor da consulta", Rejeicao),
+    (224, "Rejeição: A faixa inicial é maior que a faixa final", Rejeicao),
+    (225, "Rejeição: Falha no Schema XML do lote de NFe", Rejeicao),
+    (226, "Rejeição: Código da UF do Emitente diverge da UF autorizadora", Rejeicao),
+    (227, "Rejeição: Erro na Chave de Acesso - Campo Id – falta a literal NFe", Rejeicao),
+    (228, "Rejeição: Data de Emissão muito atrasada", Rejeicao),
+    (229, "Rejeição: IE do emitente não informada", Rejeicao),
+    (230, "Rejeição: IE do emitente não cadastrada", Rejeicao),
+    (231, "Rejeição: IE do emitente não vinculada ao CNPJ", Rejeicao),
+    (232, "Rejeição: IE do destinatário não informada", Rejeicao),
+    (233, "Rejeição: IE do destinatário não cadastrada", Rejeicao),
+    (234, "Rejeição: IE do destinatário não vinculada ao CNPJ", Rejeicao),
+    (235, "Rejeição: Inscrição SUFRAMA inválida", Rejeicao),
+    (236, "Rejeição: Chave de Acesso com dígito verificador inválido", Rejeicao),
+    (237, "Rejeição: CPF do destinatário inválido", Rejeicao),
+    (238, "Rejeição: Cabeçalho - Versão do arquivo XML superior a Versão vigente", Rejeicao),
+    (239, "Rejeição: Cabeçalho - Versão do arquivo XML não suportada", Rejeicao),
+    (240, "Rejeição: Cancelamento/Inutilização - Irregularidade Fiscal do Emitente", Rejeicao),
+    (241, "Rejeição: Um número da faixa já foi utilizado", Rejeicao),
+    (242, "Rejeição: Cabeçalho - Falha no Schema XML", Rejeicao),
+    (243, "Rejeição: XML Mal Formado", Rejeicao),
+    (244, "Rejeição: CNPJ do Certificado Digital difere do CNPJ da Matriz e do CNPJ do Emitente", Rejeicao),
+    (245, "Rejeição: CNPJ Emitente não cadastrado", Rejeicao),
+    (246, "Rejeição: CNPJ Destinatário não cadastrado", Rejeicao),
+    (247, "Rejeição: Sigla da UF do Emitente diverge da UF autorizadora", Rejeicao),
+    (248, "Rejeição: UF do Recibo diverge da UF autorizadora", Rejeicao),
+    (249, "Rejeição: UF da Chave de Acesso diverge da UF autorizadora", Rejeicao),
+    (250, "Rejeição: UF diverge da UF autorizadora", Rejeicao),
+    (251, "Rejeição: UF/Município destinatário não pertence a SUFRAMA", Rejeicao),
+    (252, "Rejeição: Ambiente informado diverge do Ambiente de recebimento", Rejeicao),
+    (253, "Rejeição: Digito Verificador da chave de acesso composta inválida", Rejeicao),
+    (254, "Rejeição: NF-e complementar não possui NF referenciada", Rejeicao),
+    (255, "Rejeição: NF-e complementar possui mais de uma NF referenciada", Rejeicao),
+    (256, "Rejeição: Uma NF-e da faixa já está inutilizada na Base de dados da SEFAZ", Rejeicao),
+    (257, "Rejeição: Solicitante não habilitado para emissão da NF-e", Rejeicao),
+    (258, "Rejeição: CNPJ da consulta inválido", Rejeicao),
+    (259, "Rejeição: CNPJ da consulta não cadastrado como contribuinte na UF", Rejeicao),
+    (260, "Rejeição: IE da consulta inválida", Rejeicao),
+    (261, "Rejeição: IE da consulta não cadastrada como contribuinte na UF", Rejeicao),
+    (262, "Rejeição: UF não fornece consulta por CPF", Rejeicao),
+    (263, "Rejeição: CPF da consulta inválido", Rejeicao),
+    (264, "Rejeição: CPF da consulta não cadastrado como contribuinte na UF", Rejeicao),
+    (265, "Rejeição: Sigla da UF da consulta difere da UF do Web Service", Rejeicao),
+    (266, "Rejeição: Série utilizada não permitida no Web Service", Rejeicao),
+    (267, "Rejeição: NF Complementar referencia uma NF-e inexistente", Rejeicao),
+    (268, "Rejeição: NF Complementar referencia outra NF-e Complementar", Rejeicao),
+    (269, "Rejeição: CNPJ Emitente da NF Complementar difere do CNPJ da NF Referenciada", Rejeicao),
+    (280, "Rejeição: Certificado Transmissor inválido", Rejeicao),
+    (281, "Rejeição: Certificado Transmissor Data Validade", Rejeicao),
+    (282, "Rejeição: Certificado Transmissor sem CNPJ", Rejeicao),
+    (283, "Rejeição: Certificado Transmissor - erro Cadeia de Certificação", Rejeicao),
+    (284, "Rejeição: Certificado Transmissor revogado", Rejeicao),
+    (285, "Rejeição: Certificado Transmissor difere ICP-Brasil", Rejeicao),
+    (286, "Rejeição: Certificado Transmissor erro no acesso a LCR", Rejeicao),
+    (289, "Rejeição: Código da UF informada diverge da UF solicitada", Rejeicao),
+    (290, "Rejeição: Certificado Assinatura inválido", Rejeicao),
+    (291, "Rejeição: Certificado Assinatura Data Validade", Rejeicao),
+    (292, "Rejeição: Certificado Assinatura sem CNPJ", Rejeicao),
+    (293, "Rejeição: Certificado Assinatura - erro Cadeia de Certificação", Rejeicao),
+    (294, "Rejeição: Certificado Assinatura revogado", Rejeicao),
+    (295, "Rejeição: Certificado Assinatura difere ICP-Brasil", Rejeicao),
+    (296, "Rejeição: Certificado Assinatura erro no acesso a LCR", Rejeicao),
+    (297, "Rejeição: Assinatura difere do calculado", Rejeicao),
+    (298, "Rejeição: Assinatura difere do padrão do Projeto", Rejeicao),
+    (299, "Rejeição: XML da área de cabeçalho com codificação diferente de UTF-8", Rejeicao),
+    (301, "Uso Denegado: Irregularidade fiscal do emitente", Denegacao),
+    (302, "Uso Denegado: Irregularidade fiscal do destinatário", Denegacao),
+    (303, "Uso Denegado: Destinatário não habilitado a operar na UF", Denegacao),
+    (402, "Rejeição: XML da área de dados com codificação diferente de UTF-8", Rejeicao),
+    (404, "Rejeição: Uso de prefixo de namespace não permitido", Rejeicao),
+    (409, "Rejeição: Campo cUF inexistente no elemento nfeCabecMsg do SOAP Header", Rejeicao),
+    (410, "Rejeição: UF informada no campo cUF não é atendida pelo Web Service", Rejeicao),
+    (411, "Rejeição: Campo versaoDados inexistente no elemento nfeCabecMsg do SOAP Header", Rejeicao),
+    (462, "Rejeição: Código Identificador do CSC no QR-Code não cadastrado na SEFAZ", Rejeicao),
+    (464, "Rejeição: Código de Hash no QR-Code difere do calculado", Rejeicao),
+    (502, "Rejeição: Erro na Chave de Acesso - Campo Id não corresponde à concatenação dos campos correspondentes", Rejeicao),
+    (531, "Rejeição: Total da BC ICMS difere do somatório dos itens", Rejeicao),
+    (532, "Rejeição: Total do ICMS difere do somatório dos itens", Rejeicao),
+    (539, "Rejeição: Duplicidade de NF-e com diferença na Chave de Acesso", Rejeicao),
+    (564, "Rejeição: Total do Produto / Serviço difere do somatório dos itens", Rejeicao),
+    (573, "Rejeição: Duplicidade de Evento", Rejeicao),
+    (574, "Rejeição: O autor do evento diverge do emissor da NF-e", Rejeicao),
+    (577, "Rejeição: A data do evento não pode ser menor que a data de emissão da NF-e", Rejeicao),
+    (578, "Rejeição: A data do evento não pode ser maior que a data do processamento", Rejeicao),
+    (579, "Rejeição: A data do evento não pode ser menor que a data de autorização para NF-e não emitida em contingência", Rejeicao),
+    (580, "Rejeição: O evento exige uma NF-e autorizada", Rejeicao),
+    (587, "Rejeição: Usar somente o namespace padrão da NF-e", Rejeicao),
+    (588, "Rejeição: Não é permitida a presença de caracteres de edição no início/fim da mensagem ou entre as tags da mensagem", Rejeicao),
+    (590, "Rejeição: Informado CST para emissor do Simples Nacional (CRT=1)", Rejeicao),
+    (591, "Rejeição: Informado CSOSN para emissor que não é do Simples Nacional (CRT diferente de 1)", Rejeicao),
+    (594, "Rejeição: O número de sequencia do evento informado é maior que o permitido", Rejeicao),
+    (610, "Rejeição: Total da NF difere do somatório dos Valores compõe o valor Total da NF", Rejeicao),
+    (613, "Rejeição: Chave de Acesso difere da existente em BD", Rejeicao),
+    (614, "Rejeição: Chave de Acesso inválida (Código UF inválido)", Rejeicao),
+    (615, "Rejeição: Chave de Acesso inválida (Ano menor que 06 ou Ano maior que Ano corrente)", Rejeicao),
+    (616, "Rejeição: Chave de Acesso inválida (Mês menor que 1 ou Mês maior que 12)", Rejeicao),
+    (617, "Rejeição: Chave de Acesso inválida (CNPJ zerado ou dígito inválido)", Rejeicao),
+    (618, "Rejeição: Chave de Acesso inválida (modelo diferente de 55 e 65)", Rejeicao),
+    (619, "Rejeição: Chave de Acesso inválida (número NF = 0)", Rejeicao),
+    (629, "Rejeição: Valor do Produto difere do produto Valor Unitário de Comercialização e Quantidade Comercial", Rejeicao),
+    (632, "Rejeição: Solicitação fora de prazo, a NF-e não está mais disponível para download", Rejeicao),
+    (656, "Rejeição: Consumo Indevido", Transitoria),
+    (704, "Rejeição: NFC-e com Data-Hora de emissão atrasada", Rejeicao),
+    (778, "Rejeição: Informado NCM inexistente", Rejeicao),
+    (999, "Rejeição: Erro não catalogado", Rejeicao),
+];
+
+impl CodigoStatus {
+    #[inline]
+    fn make(
+        &(codigo, descricao, classificacao): &(u16, &'static str, ClassificacaoStatus),
+    ) -> Self {
+        Self {
+            codigo,
+            descricao,
+            classificacao,
+        }
+    }
+
+    /// Obtém código de status do catálogo, se existir.
+    pub fn from_codigo(codigo: u16) -> Option<Self> {
+        CODIGOS
+            .binary_search_by_key(&codigo, |&(codigo, _, _)| codigo)
+            .ok()
+            .map(|indice| Self::make(&CODIGOS[indice]))
+    }
+
+    /// Classifica código de status, considerando códigos fora do catálogo como rejeição, inclusive
+    /// na faixa 1xx, para que um código desconhecido nunca seja tratado como sucesso.
+    pub fn classificar(codigo: u16) -> ClassificacaoStatus {
+        Self::from_codigo(codigo).map_or(Rejeicao, |status| status.classificacao)
+    }
+
+    /// Lista todos os códigos de status do catálogo.
+    pub fn todos() -> impl Iterator<Item = Self> {
+        CODIGOS.iter().map(Self::make)
+    }
+}
+
+impl fmt::Display for CodigoStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.codigo, self.descricao)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogo_ordenado() {
+        assert!(CODIGOS.windows(2).all(|par| par[0].0 < par[1].0));
+    }
+
+    #[test]
+    fn from_codigo() {
+        let status = CodigoStatus::from_codigo(100).unwrap();
+        assert_eq!(status.codigo, 100);
+        assert_eq!(status.descricao, "Autorizado o uso da NF-e");
+        assert_eq!(status.classificacao, Sucesso);
+        assert!(CodigoStatus::from_codigo(141).is_none());
+        assert!(CodigoStatus::from_codigo(0).is_none());
+    }
+
+    #[test]
+    fn classificar() {
+        assert_eq!(CodigoStatus::classificar(105), Transitoria);
+        assert_eq!(CodigoStatus::classificar(205), Rejeicao);
+        assert_eq!(CodigoStatus::classificar(999), Rejeicao);
+        assert_eq!(CodigoStatus::classificar(141), Rejeicao);
+    }
+
+    #[test]
+    fn todos() {
+        assert_eq!(CodigoStatus::todos().count(), CODIGOS.len());
+        assert!(CodigoStatus::todos()
+            .all(|status| CodigoStatus::from_codigo(status.codigo) == Some(status)));
+    }
+
+    #[test]
+    fn exibir() {
+        assert_eq!(
+            CodigoStatus::from_codigo(218).unwrap().to_string(),
+            "218 - Rejeição: NF-e já está cancelada na base de dados da SEFAZ"
+        );
+    }
+}
@@ -134,6 +134,41 @@ impl Uf {
         }
     }
 
+    #[inline]
+    pub fn from_cuf(cuf: u8) -> Option<Self> {
+        use super::Uf::*;
+        match cuf {
+            11 => Some(Ro),
+            12 => Some(Ac),
+            13 => Some(Am),
+            14 => Some(Rr),
+            15 => Some(Pa),
+            16 => Some(Ap),
+            17 => Some(To),
+            21 => Some(Ma),
+            22 => Some(Pi),
+            23 => Some(Ce),
+            24 => Some(Rn),
+            25 => Some(Pb),
+            26 => Some(Pe),
+            27 => Some(Al),
+            28 => Some(Se),
+            29 => Some(Ba),
+            31 => Some(Mg),
+            32 => Some(Es),
+            33 => Some(Rj),
+            35 => Some(Sp),
+            41 => Some(Pr),
+            42 => Some(Sc),
+            43 => Some(Rs),
+            50 => Some(Ms),
+            51 => Some(Mt),
+            52 => Some(Go),
+            53 => Some(Df),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn cuf(&self) -> u8 {
         use super::Uf::*;
@@ -238,6 +273,16 @@ impl Modelo {
         }
     }
 
+    #[inline]
+    pub fn from_codigo(codigo: u8) -> Option<Self> {
+        use super::Modelo::*;
+        match codigo {
+            55 => Some(Nfe),
+            65 => Some(Nfce),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn codigo(&self) -> u8 {
         use super::Modelo::*;
@@ -254,6 +299,38 @@ impl fmt::Display for Modelo {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Processamento {
+    Assincrono,
+    Sincrono,
+}
+
+impl Processamento {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        use super::Processamento::*;
+        match *self {
+            Assincrono => "Assíncrono",
+            Sincrono => "Síncrono",
+        }
+    }
+
+    #[inline]
+    pub fn ind_sinc(&self) -> u8 {
+        use super::Processamento::*;
+        match *self {
+            Assincrono => 0,
+            Sincrono => 1,
+        }
+    }
+}
+
+impl fmt::Display for Processamento {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Servico {
     StatusServico,
@@ -328,9 +405,9 @@ impl Servico {
             Envio => Some("NFeAutorizacao4"),
             Cce => Some("NFeRecepcaoEvento4"),
             Epec => Some("NFeRecepcaoEvento4"),
-            Manifestacao => Some("RecepcaoEvento"),
-            Cancelamento => Some("RecepcaoEvento"),
-            CancelamentoSubstituicao => Some("RecepcaoEvento"),
+            Manifestacao => Some("NFeRecepcaoEvento4"),
+            Cancelamento => Some("NFeRecepcaoEvento4"),
+            CancelamentoSubstituicao => Some("NFeRecepcaoEvento4"),
             Inutilizacao => Some("NFeInutilizacao4"),
             DistribuicaoDfe => Some("NFeDistribuicaoDFe"),
             UrlQrCode => Some("NfeConsultaQR"),
@@ -338,6 +415,23 @@ impl Servico {
         }
     }
 
+    /// Indica se o serviço apenas consulta dados, podendo ser repetido sem efeitos colaterais.
+    #[inline]
+    pub fn idempotente(&self) -> bool {
+        use super::Servico::*;
+        match *self {
+            StatusServico | ConsultaProtocolo | ConsultaAutorizacao | ConsultaCadastro
+            | DistribuicaoDfe | UrlQrCode | UrlConsultaNfce => true,
+            Envio
+            | Cce
+            | Epec
+            | Manifestacao
+            | Cancelamento
+            | CancelamentoSubstituicao
+            | Inutilizacao => false,
+        }
+    }
+
     #[inline]
     pub fn versao_url(&self) -> VersaoUrl {
         use super::Servico::*;
@@ -361,6 +455,98 @@ impl Servico {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TipoEvento {
+    Cancelamento,
+    CancelamentoSubstituicao,
+    CartaCorrecao,
+    ConfirmacaoOperacao,
+    CienciaOperacao,
+    DesconhecimentoOperacao,
+    OperacaoNaoRealizada,
+    Epec,
+}
+
+impl TipoEvento {
+    #[inline]
+    pub fn codigo(&self) -> u32 {
+        use super::TipoEvento::*;
+        match *self {
+            Cancelamento => 110111,
+            CancelamentoSubstituicao => 110112,
+            CartaCorrecao => 110110,
+            ConfirmacaoOperacao => 210200,
+            CienciaOperacao => 210210,
+            DesconhecimentoOperacao => 210220,
+            OperacaoNaoRealizada => 210240,
+            Epec => 110140,
+        }
+    }
+
+    #[inline]
+    pub fn descricao(&self) -> &str {
+        use super::TipoEvento::*;
+        match *self {
+            Cancelamento => "Cancelamento",
+            CancelamentoSubstituicao => "Cancelamento por substituicao",
+            CartaCorrecao => "Carta de Correcao",
+            ConfirmacaoOperacao => "Confirmacao da Operacao",
+            CienciaOperacao => "Ciencia da Operacao",
+            DesconhecimentoOperacao => "Desconhecimento da Operacao",
+            OperacaoNaoRealizada => "Operacao nao Realizada",
+            Epec => "EPEC",
+        }
+    }
+
+    #[inline]
+    pub fn servico(&self) -> Servico {
+        use super::TipoEvento::*;
+        match *self {
+            Cancelamento => Servico::Cancelamento,
+            CancelamentoSubstituicao => Servico::CancelamentoSubstituicao,
+            CartaCorrecao => Servico::Cce,
+            ConfirmacaoOperacao
+            | CienciaOperacao
+            | DesconhecimentoOperacao
+            | OperacaoNaoRealizada => Servico::Manifestacao,
+            Epec => Servico::Epec,
+        }
+    }
+}
+
+impl fmt::Display for TipoEvento {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.descricao())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Manifestacao {
+    Confirmacao,
+    Ciencia,
+    Desconhecimento,
+    OperacaoNaoRealizada,
+}
+
+impl Manifestacao {
+    #[inline]
+    pub fn tipo_evento(&self) -> TipoEvento {
+        use super::Manifestacao::*;
+        match *self {
+            Confirmacao => TipoEvento::ConfirmacaoOperacao,
+            Ciencia => TipoEvento::CienciaOperacao,
+            Desconhecimento => TipoEvento::DesconhecimentoOperacao,
+            OperacaoNaoRealizada => TipoEvento::OperacaoNaoRealizada,
+        }
+    }
+}
+
+impl fmt::Display for Manifestacao {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tipo_evento())
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum TipoDocumento {
     Cpf,
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+    version::{TLS12, TLS13},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme, SupportedCipherSuite,
+};
+
+use crate::{client::ICP_BRASIL, ClientError, Pkcs12Certificate};
+
+/// Suítes de cifra em ordem de preferência. Boa parte dos webservices da SEFAZ só negocia TLS 1.2
+/// com ECDHE-RSA e AES-GCM, então essas suítes são oferecidas primeiro.
+static CIPHER_SUITES: &[SupportedCipherSuite] = &[
+    ring::cipher_suite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+    ring::cipher_suite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+    ring::cipher_suite::TLS13_AES_256_GCM_SHA384,
+    ring::cipher_suite::TLS13_AES_128_GCM_SHA256,
+    ring::cipher_suite::TLS13_CHACHA20_POLY1305_SHA256,
+    ring::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+    ring::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+    ring::cipher_suite::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+    ring::cipher_suite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+];
+
+/// Monta a configuração do rustls com as suítes aceitas pela SEFAZ, os certificados raiz do
+/// sistema, da ICP-Brasil e os informados, e a identidade do certificado PKCS #12.
+pub(crate) fn configurar(
+    pkcs12: Option<Pkcs12Certificate>,
+    certificados_raiz: &[Vec<u8>],
+    aceitar_certificados_invalidos: bool,
+) -> Result<ClientConfig, ClientError> {
+    // Servidores IIS da SEFAZ nem sempre aceitam X25519 no TLS 1.2, então as curvas NIST vêm antes.
+    let provider = Arc::new(CryptoProvider {
+        cipher_suites: CIPHER_SUITES.to_vec(),
+        kx_groups: vec![
+            ring::kx_group::SECP256R1,
+            ring::kx_group::SECP384R1,
+            ring::kx_group::X25519,
+        ],
+        ..ring::default_provider()
+    });
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&TLS12, &TLS13])?;
+    let builder = if aceitar_certificados_invalidos {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(SemVerificacao(provider)))
+    } else {
+        builder.with_root_certificates(raizes(certificados_raiz)?)
+    };
+    Ok(match pkcs12 {
+        Some(pkcs12) => {
+            let (cadeia, chave) = pkcs12.into_rustls();
+            builder.with_client_auth_cert(cadeia, chave)?
+        }
+        None => builder.with_no_client_auth(),
+    })
+}
+
+fn raizes(certificados: &[Vec<u8>]) -> Result<RootCertStore, ClientError> {
+    let mut raizes = RootCertStore::empty();
+    // Certificados do sistema ilegíveis são ignorados, como no native-tls.
+    raizes.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    let certificados = certificados.iter().map(Vec::as_slice);
+    for certificado in ICP_BRASIL.iter().copied().chain(certificados) {
+        if certificado.starts_with(b"-----BEGIN") {
+            for certificado in CertificateDer::pem_slice_iter(certificado) {
+                raizes.add(certificado?)?;
+            }
+        } else {
+            raizes.add(CertificateDer::from(certificado))?;
+        }
+    }
+    Ok(raizes)
+}
+
+/// Verificador que aceita qualquer certificado de servidor, checando apenas as assinaturas do
+/// handshake.
+#[derive(Debug)]
+struct SemVerificacao(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for SemVerificacao {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkcs12() -> Pkcs12Certificate {
+        Pkcs12Certificate::from_bytes(fiscalidade_mock::PKCS12, fiscalidade_mock::SENHA_PKCS12)
+            .unwrap()
+    }
+
+    #[test]
+    fn raizes_pem_e_der() {
+        let sistema = raizes(&[]).unwrap().len();
+        let pem = fiscalidade_mock::CERTIFICADO_AC.to_vec();
+        let der = CertificateDer::pem_slice_iter(&pem)
+            .next()
+            .unwrap()
+            .unwrap()
+            .to_vec();
+        assert_eq!(raizes(&[pem]).unwrap().len(), sistema + 1);
+        assert_eq!(raizes(&[der]).unwrap().len(), sistema + 1);
+    }
+
+    #[test]
+    fn raizes_invalidas() {
+        assert!(raizes(&[b"certificado".to_vec()]).is_err());
+        assert!(matches!(
+            raizes(&[b"-----BEGIN CERTIFICATE-----\n!!\n-----END CERTIFICATE-----\n".to_vec()]),
+            Err(ClientError::Pem(_))
+        ));
+    }
+
+    #[test]
+    fn configurar() {
+        let config = super::configurar(Some(pkcs12()), &[], false).unwrap();
+        assert!(config.client_auth_cert_resolver.has_certs());
+        assert_eq!(
+            config.crypto_provider().cipher_suites[0],
+            ring::cipher_suite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+        );
+        let config = super::configurar(None, &[], true).unwrap();
+        assert!(!config.client_auth_cert_resolver.has_certs());
+    }
+}
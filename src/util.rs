@@ -1,6 +1,7 @@
+use chrono::{DateTime, Local, TimeDelta};
 use regex::Regex;
 
-use crate::tipos::{Ambiente, Uf};
+use crate::tipos::{Ambiente, Documento, Modelo, Uf};
 
 /// Valida chave de nota usando regra `^[0-9]{44}$` extraída de arquivo "tiposBasico_v4.00.xsd" da SEFAZ.
 pub fn validar_chave(chave: &str) -> bool {
@@ -16,6 +17,105 @@ pub fn validar_recibo(chave: &str) -> bool {
         .unwrap_or_default()
 }
 
+/// Valida identificador de lote usando regra `^[0-9]{1,15}$` extraída de arquivo "tiposBasico_v4.00.xsd" da SEFAZ.
+pub fn validar_lote(lote: &str) -> bool {
+    Regex::new("^[0-9]{1,15}$")
+        .map(|re| re.is_match(lote))
+        .unwrap_or_default()
+}
+
+/// Valida número de protocolo usando regra `^[0-9]{15}$` extraída de arquivo "tiposBasico_v4.00.xsd" da SEFAZ.
+pub fn validar_protocolo(protocolo: &str) -> bool {
+    Regex::new("^[0-9]{15}$")
+        .map(|re| re.is_match(protocolo))
+        .unwrap_or_default()
+}
+
+/// Valida CNPJ usando regra `^[0-9]{14}$` extraída de arquivo "tiposBasico_v4.00.xsd" da SEFAZ.
+pub fn validar_cnpj(cnpj: &str) -> bool {
+    Regex::new("^[0-9]{14}$")
+        .map(|re| re.is_match(cnpj))
+        .unwrap_or_default()
+}
+
+/// Valida faixa de numeração de notas, que deve estar entre 1 e 999999999.
+#[inline]
+pub fn validar_numeracao(n_ini: u32, n_fin: u32) -> bool {
+    n_ini >= 1 && n_ini <= n_fin && n_fin <= 999_999_999
+}
+
+/// Valida justificativa de eventos e inutilização, que deve conter entre 15 e 255 caracteres.
+#[inline]
+pub fn validar_justificativa(justificativa: &str) -> bool {
+    (15..=255).contains(&justificativa.trim().chars().count())
+}
+
+/// Valida texto de correção da CC-e, que deve conter entre 15 e 1000 caracteres.
+#[inline]
+pub fn validar_correcao(correcao: &str) -> bool {
+    (15..=1000).contains(&correcao.trim().chars().count())
+}
+
+/// Valida número sequencial de evento da CC-e, que deve estar entre 1 e 20.
+#[inline]
+pub fn validar_sequencia_cce(sequencia: u8) -> bool {
+    (1..=20).contains(&sequencia)
+}
+
+/// Valida se nota emitida na data/hora informada, no formato `AAAA-MM-DDThh:mm:ssTZD`, ainda está
+/// dentro do prazo de 24 horas para cancelamento.
+pub fn validar_prazo_cancelamento(dh_emi: &str) -> bool {
+    DateTime::parse_from_rfc3339(dh_emi)
+        .map(|dh_emi| Local::now().signed_duration_since(dh_emi) <= TimeDelta::hours(24))
+        .unwrap_or_default()
+}
+
+/// Extrai CNPJ do emitente contido na chave de nota.
+#[inline]
+pub fn extrair_cnpj(chave: &str) -> &str {
+    chave.get(6..20).unwrap_or_default()
+}
+
+/// Extrai documento do emitente contido na chave de nota. Emitentes pessoa física, e.g. produtores
+/// rurais, têm o CPF precedido de `000` no lugar do CNPJ, identificado pelos dígitos verificadores.
+pub fn extrair_emitente(chave: &str) -> Documento<'_> {
+    let cnpj = extrair_cnpj(chave);
+    match cnpj.strip_prefix("000") {
+        Some(cpf) if validar_digitos_cpf(cpf) && !validar_digitos_cnpj(cnpj) => {
+            Documento::from_cpf(cpf)
+        }
+        _ => Documento::from_cnpj(cnpj),
+    }
+}
+
+/// Extrai modelo da nota contido na chave.
+#[inline]
+pub fn extrair_modelo(chave: &str) -> Option<Modelo> {
+    chave
+        .get(20..22)?
+        .parse()
+        .ok()
+        .and_then(Modelo::from_codigo)
+}
+
+/// Extrai UF do emitente contida na chave de nota.
+#[inline]
+pub fn extrair_uf(chave: &str) -> Option<Uf> {
+    chave.get(..2)?.parse().ok().and_then(Uf::from_cuf)
+}
+
+/// Gera data/hora atual no formato `AAAA-MM-DDThh:mm:ssTZD` usado pela SEFAZ.
+#[inline]
+pub fn data_hora_atual() -> String {
+    Local::now().format("%Y-%m-%dT%H:%M:%S%:z").to_string()
+}
+
+/// Gera identificador de lote com 15 dígitos a partir da data/hora atual.
+#[inline]
+pub fn gerar_id_lote() -> String {
+    Local::now().format("%y%m%d%H%M%S%3f").to_string()
+}
+
 /// Valida se UF é está presente na lista de UFs válidas.
 #[inline]
 pub fn validar_uf(uf: &str) -> bool {
@@ -27,3 +127,144 @@ pub fn validar_uf(uf: &str) -> bool {
 pub fn validar_ambiente(ambiente: &str) -> bool {
     Ambiente::from_str(ambiente).is_some()
 }
+
+/// Calcula dígito verificador módulo 11 usado por CPF e CNPJ a partir dos pesos informados.
+#[inline]
+fn digito_modulo11(digitos: &[u32], pesos: &[u32]) -> u32 {
+    let resto = digitos.iter().zip(pesos).map(|(d, p)| d * p).sum::<u32>() % 11;
+    if resto < 2 {
+        0
+    } else {
+        11 - resto
+    }
+}
+
+/// Valida dígitos verificadores de CPF.
+fn validar_digitos_cpf(cpf: &str) -> bool {
+    let digitos: Vec<u32> = cpf.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digitos.len() != 11 || cpf.len() != 11 {
+        return false;
+    }
+    digito_modulo11(&digitos[..9], &[10, 9, 8, 7, 6, 5, 4, 3, 2]) == digitos[9]
+        && digito_modulo11(&digitos[..10], &[11, 10, 9, 8, 7, 6, 5, 4, 3, 2]) == digitos[10]
+}
+
+/// Valida dígitos verificadores de CNPJ.
+fn validar_digitos_cnpj(cnpj: &str) -> bool {
+    let digitos: Vec<u32> = cnpj.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digitos.len() != 14 || cnpj.len() != 14 {
+        return false;
+    }
+    digito_modulo11(&digitos[..12], &[5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]) == digitos[12]
+        && digito_modulo11(&digitos[..13], &[6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]) == digitos[13]
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Utc};
+
+    use super::*;
+    use crate::tipos::TipoDocumento;
+
+    #[test]
+    fn chave_e_protocolo() {
+        assert!(validar_chave(
+            "51240111222333000181550010000000011000000010"
+        ));
+        assert!(!validar_chave(
+            "5124011122233300018155001000000001100000001"
+        ));
+        assert!(!validar_chave(
+            "5124011122233300018155001000000001100000001A"
+        ));
+        assert!(validar_protocolo("151240000000001"));
+        assert!(!validar_protocolo("15124000000001"));
+    }
+
+    #[test]
+    fn justificativa() {
+        // Espaços nas extremidades não contam e caracteres acentuados contam como um.
+        assert!(!validar_justificativa(&format!("  {}  ", "a".repeat(14))));
+        assert!(validar_justificativa(&format!("  {}  ", "a".repeat(15))));
+        assert!(validar_justificativa(&"ç".repeat(255)));
+        assert!(!validar_justificativa(&"a".repeat(256)));
+    }
+
+    #[test]
+    fn prazo_cancelamento() {
+        let fuso = FixedOffset::west_opt(4 * 3600).unwrap();
+        let data = |horas| {
+            (Utc::now() - TimeDelta::hours(horas))
+                .with_timezone(&fuso)
+                .format("%Y-%m-%dT%H:%M:%S%:z")
+                .to_string()
+        };
+        assert!(validar_prazo_cancelamento(&data(23)));
+        assert!(!validar_prazo_cancelamento(&data(25)));
+        assert!(!validar_prazo_cancelamento("2024-01-10 10:00:00"));
+    }
+
+    #[test]
+    fn emitente() {
+        let cnpj = extrair_emitente("51240111222333000181550010000000011000000010");
+        assert_eq!(cnpj.tipo(), TipoDocumento::Cnpj);
+        assert_eq!(cnpj.as_str(), "11222333000181");
+        let cpf = extrair_emitente("51240100052998224725550010000000011000000010");
+        assert_eq!(cpf.tipo(), TipoDocumento::Cpf);
+        assert_eq!(cpf.as_str(), "52998224725");
+        // CNPJ válido iniciado por zeros não é confundido com CPF.
+        let cnpj = extrair_emitente("51240100000000000191550010000000011000000010");
+        assert_eq!(cnpj.tipo(), TipoDocumento::Cnpj);
+        assert_eq!(cnpj.as_str(), "00000000000191");
+    }
+
+    #[test]
+    fn uf_da_chave() {
+        assert_eq!(
+            extrair_uf("51240111222333000181550010000000011000000010"),
+            Some(Uf::Mt)
+        );
+        assert_eq!(extrair_uf("99"), None);
+        assert_eq!(extrair_uf(""), None);
+    }
+
+    #[test]
+    fn correcao_e_sequencia() {
+        assert!(!validar_correcao(&format!(" {} ", "a".repeat(14))));
+        assert!(validar_correcao(&"a".repeat(15)));
+        assert!(validar_correcao(&"é".repeat(1000)));
+        assert!(!validar_correcao(&"a".repeat(1001)));
+        assert!(!validar_sequencia_cce(0));
+        assert!(validar_sequencia_cce(1));
+        assert!(validar_sequencia_cce(20));
+        assert!(!validar_sequencia_cce(21));
+    }
+
+    #[test]
+    fn numeracao() {
+        assert!(validar_numeracao(1, 1));
+        assert!(validar_numeracao(10, 999_999_999));
+        assert!(!validar_numeracao(0, 10));
+        assert!(!validar_numeracao(20, 10));
+        assert!(!validar_numeracao(1, 1_000_000_000));
+        assert!(validar_cnpj("11222333000181"));
+        assert!(!validar_cnpj("11.222.333/0001-81"));
+    }
+
+    #[test]
+    fn modelo_da_chave() {
+        assert_eq!(
+            extrair_modelo("51240111222333000181550010000000011000000010"),
+            Some(Modelo::Nfe)
+        );
+        assert_eq!(
+            extrair_modelo("51240111222333000181650010000000011000000014"),
+            Some(Modelo::Nfce)
+        );
+        assert_eq!(
+            extrair_modelo("51240111222333000181570010000000011000000010"),
+            None
+        );
+        assert_eq!(extrair_modelo("5124011122233300018"), None);
+    }
+}
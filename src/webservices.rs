@@ -51,7 +51,15 @@ impl WebServices {
     pub fn get_from(&self, section: &str, key: &str) -> Option<&str> {
         let table = self.inner.as_table()?;
         let pairs = table.get(section)?;
-        let value = pairs.get(key)?;
+        // Algumas seções do arquivo de webservices grafam as chaves com caixa diferente, e.g.
+        // "NfeAutorizacao_4.00" e "NFeAutorizacao_4.00", então a busca é refeita ignorando caixa.
+        let value = pairs.get(key).or_else(|| {
+            pairs
+                .as_table()?
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v)
+        })?;
         value.as_str()
     }
 }
@@ -0,0 +1,129 @@
+//! Compara as assinaturas geradas pela biblioteca com as geradas pela implementação XMLDSig do JDK
+//! (Apache Santuario), em `tests/fixtures/assinatura`. Veja `Assinar.java` para regerá-las.
+
+use fiscalidade::{verificar_assinatura, Assinador, AssinaturaError, Pkcs12Certificate};
+use fiscalidade_mock::{CNPJ, PKCS12, SENHA_PKCS12};
+
+/// Valor da tag informada, desconsiderando quebras de linha do base64.
+fn valor(xml: &str, tag: &str) -> String {
+    let inicio = xml.find(&format!("<{}>", tag)).unwrap() + tag.len() + 2;
+    let fim = inicio + xml[inicio..].find(&format!("</{}>", tag)).unwrap();
+    xml[inicio..fim]
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect()
+}
+
+fn comparar(xml: &str, assinado: &str, id: &str) -> anyhow::Result<()> {
+    let pkcs12 = Pkcs12Certificate::from_bytes(PKCS12, SENHA_PKCS12)?;
+    let xml = pkcs12.assinar(xml, id)?;
+    assert_eq!(valor(&xml, "DigestValue"), valor(assinado, "DigestValue"));
+    assert_eq!(
+        valor(&xml, "SignatureValue"),
+        valor(assinado, "SignatureValue")
+    );
+    assert_eq!(
+        valor(&xml, "X509Certificate"),
+        valor(assinado, "X509Certificate")
+    );
+    let assinante = verificar_assinatura(assinado)?;
+    assert_eq!(assinante.cnpj.as_deref(), Some(CNPJ));
+    Ok(())
+}
+
+#[test]
+fn nfe() -> anyhow::Result<()> {
+    comparar(
+        include_str!("fixtures/assinatura/nfe.xml"),
+        include_str!("fixtures/assinatura/nfe-assinado.xml"),
+        "NFe51240111222333000181550010000000011000000010",
+    )
+}
+
+#[test]
+fn evento() -> anyhow::Result<()> {
+    comparar(
+        include_str!("fixtures/assinatura/evento.xml"),
+        include_str!("fixtures/assinatura/evento-assinado.xml"),
+        "ID1101115124011122233300018155001000000001100000001001",
+    )
+}
+
+#[test]
+fn inut_nfe() -> anyhow::Result<()> {
+    comparar(
+        include_str!("fixtures/assinatura/inut_nfe.xml"),
+        include_str!("fixtures/assinatura/inut_nfe-assinado.xml"),
+        "ID51241122233300018155001000000010000000020",
+    )
+}
+
+#[test]
+fn nfe_namespace_ancestral() -> anyhow::Result<()> {
+    // O SignedInfo canônico herda o `xmlns:xsi` declarado no lote, e não apenas o namespace
+    // XMLDSig da tag Signature.
+    let xml = include_str!("fixtures/assinatura/nfe_xsi.xml");
+    let id = "NFe51240111222333000181550010000000011000000010";
+    comparar(
+        xml,
+        include_str!("fixtures/assinatura/nfe_xsi-assinado.xml"),
+        id,
+    )?;
+    let assinado = Pkcs12Certificate::from_bytes(PKCS12, SENHA_PKCS12)?.assinar(xml, id)?;
+    assert_eq!(verificar_assinatura(&assinado)?.cnpj.as_deref(), Some(CNPJ));
+    Ok(())
+}
+
+/// NF-e assinada e o elemento `infNFe` original contido nela.
+fn nfe_assinada() -> (&'static str, &'static str) {
+    let xml = include_str!("fixtures/assinatura/nfe-assinado.xml");
+    let inicio = xml.find("<infNFe").unwrap();
+    let fim = xml.find("</infNFe>").unwrap() + "</infNFe>".len();
+    (xml, &xml[inicio..fim])
+}
+
+#[test]
+fn wrapping_id_duplicado() {
+    // O infNFe original é escondido em outro elemento e um forjado ocupa o seu lugar.
+    let (xml, inf_nfe) = nfe_assinada();
+    let forjado = inf_nfe.replace("<vNF>10.00</vNF>", "<vNF>1.00</vNF>");
+    let xml = xml.replacen(
+        inf_nfe,
+        &format!("<infAdic>{}</infAdic>{}", inf_nfe, forjado),
+        1,
+    );
+    assert!(matches!(
+        verificar_assinatura(&xml),
+        Err(AssinaturaError::IdDuplicado(_))
+    ));
+}
+
+#[test]
+fn wrapping_fora_de_posicao() {
+    let (xml, inf_nfe) = nfe_assinada();
+    let xml = xml.replacen(inf_nfe, &format!("<infAdic>{}</infAdic>", inf_nfe), 1);
+    assert!(matches!(
+        verificar_assinatura(&xml),
+        Err(AssinaturaError::ReferenciaInvalida(_))
+    ));
+}
+
+#[test]
+fn transformacoes_invalidas() {
+    let (xml, _) = nfe_assinada();
+    let c14n = "<Transform Algorithm=\"http://www.w3.org/TR/2001/REC-xml-c14n-20010315\"/>";
+    assert!(xml.contains(c14n));
+    assert!(matches!(
+        verificar_assinatura(&xml.replacen(c14n, "", 1)),
+        Err(AssinaturaError::TagNaoEncontrada("Transform"))
+    ));
+    let exclusivo = "<Transform Algorithm=\"http://www.w3.org/2001/10/xml-exc-c14n#\"/>";
+    assert!(matches!(
+        verificar_assinatura(&xml.replacen(c14n, exclusivo, 1)),
+        Err(AssinaturaError::AlgoritmoNaoSuportado(_))
+    ));
+    assert!(matches!(
+        verificar_assinatura(&xml.replacen(c14n, &format!("{}{}", c14n, c14n), 1)),
+        Err(AssinaturaError::AlgoritmoNaoSuportado(_))
+    ));
+}
@@ -0,0 +1,101 @@
+// Gera os XMLs assinados usados como referência nos testes de assinatura, usando a implementação
+// XMLDSig do JDK (Apache Santuario), independente da implementação da biblioteca.
+//
+// Uso, a partir deste diretório:
+//
+//   java -Dcom.sun.org.apache.xml.internal.security.ignoreLineBreaks=true Assinar.java \
+//     ../../../fiscalidade-mock/resources/cliente.pfx mock nfe.xml nfe_xsi.xml evento.xml inut_nfe.xml
+//
+// Cada arquivo `nome.xml` gera `nome-assinado.xml`, com a tag `Signature` inserida após o elemento
+// que possui o atributo `Id`, como exigido pela SEFAZ.
+
+import java.io.File;
+import java.io.FileInputStream;
+import java.security.KeyStore;
+import java.security.PrivateKey;
+import java.security.cert.X509Certificate;
+import java.util.Collections;
+import java.util.List;
+
+import javax.xml.crypto.dsig.CanonicalizationMethod;
+import javax.xml.crypto.dsig.DigestMethod;
+import javax.xml.crypto.dsig.Reference;
+import javax.xml.crypto.dsig.SignatureMethod;
+import javax.xml.crypto.dsig.SignedInfo;
+import javax.xml.crypto.dsig.Transform;
+import javax.xml.crypto.dsig.XMLSignature;
+import javax.xml.crypto.dsig.XMLSignatureFactory;
+import javax.xml.crypto.dsig.dom.DOMSignContext;
+import javax.xml.crypto.dsig.keyinfo.KeyInfo;
+import javax.xml.crypto.dsig.keyinfo.KeyInfoFactory;
+import javax.xml.crypto.dsig.spec.C14NMethodParameterSpec;
+import javax.xml.crypto.dsig.spec.TransformParameterSpec;
+import javax.xml.parsers.DocumentBuilderFactory;
+import javax.xml.transform.OutputKeys;
+import javax.xml.transform.TransformerFactory;
+import javax.xml.transform.dom.DOMSource;
+import javax.xml.transform.stream.StreamResult;
+
+import org.w3c.dom.Document;
+import org.w3c.dom.Element;
+import org.w3c.dom.NodeList;
+
+public class Assinar {
+    public static void main(String[] args) throws Exception {
+        KeyStore pkcs12 = KeyStore.getInstance("PKCS12");
+        try (FileInputStream entrada = new FileInputStream(args[0])) {
+            pkcs12.load(entrada, args[1].toCharArray());
+        }
+        String alias = pkcs12.aliases().nextElement();
+        PrivateKey chave = (PrivateKey) pkcs12.getKey(alias, args[1].toCharArray());
+        X509Certificate certificado = (X509Certificate) pkcs12.getCertificate(alias);
+        for (int i = 2; i < args.length; i++) {
+            assinar(new File(args[i]), chave, certificado);
+        }
+    }
+
+    static void assinar(File arquivo, PrivateKey chave, X509Certificate certificado)
+            throws Exception {
+        DocumentBuilderFactory fabrica = DocumentBuilderFactory.newInstance();
+        fabrica.setNamespaceAware(true);
+        Document doc = fabrica.newDocumentBuilder().parse(arquivo);
+        Element elemento = null;
+        NodeList elementos = doc.getElementsByTagName("*");
+        for (int i = 0; i < elementos.getLength(); i++) {
+            Element candidato = (Element) elementos.item(i);
+            if (candidato.hasAttribute("Id")) {
+                elemento = candidato;
+                break;
+            }
+        }
+        String id = elemento.getAttribute("Id");
+        elemento.setIdAttribute("Id", true);
+
+        XMLSignatureFactory xmldsig = XMLSignatureFactory.getInstance("DOM");
+        List<Transform> transforms = List.of(
+                xmldsig.newTransform(Transform.ENVELOPED, (TransformParameterSpec) null),
+                xmldsig.newTransform(CanonicalizationMethod.INCLUSIVE,
+                        (TransformParameterSpec) null));
+        Reference referencia = xmldsig.newReference("#" + id,
+                xmldsig.newDigestMethod(DigestMethod.SHA1, null), transforms, null, null);
+        SignedInfo signedInfo = xmldsig.newSignedInfo(
+                xmldsig.newCanonicalizationMethod(CanonicalizationMethod.INCLUSIVE,
+                        (C14NMethodParameterSpec) null),
+                xmldsig.newSignatureMethod(SignatureMethod.RSA_SHA1, null),
+                Collections.singletonList(referencia));
+        KeyInfoFactory keyInfoFactory = xmldsig.getKeyInfoFactory();
+        KeyInfo keyInfo = keyInfoFactory.newKeyInfo(Collections.singletonList(
+                keyInfoFactory.newX509Data(Collections.singletonList(certificado))));
+        DOMSignContext contexto = elemento.getNextSibling() == null
+                ? new DOMSignContext(chave, elemento.getParentNode())
+                : new DOMSignContext(chave, elemento.getParentNode(), elemento.getNextSibling());
+        XMLSignature assinatura = xmldsig.newXMLSignature(signedInfo, keyInfo);
+        assinatura.sign(contexto);
+
+        String nome = arquivo.getName().replaceFirst("\\.xml$", "-assinado.xml");
+        var transformer = TransformerFactory.newInstance().newTransformer();
+        transformer.setOutputProperty(OutputKeys.OMIT_XML_DECLARATION, "yes");
+        transformer.transform(new DOMSource(doc),
+                new StreamResult(new File(arquivo.getParentFile(), nome)));
+    }
+}
@@ -0,0 +1,962 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use fiscalidade::{
+    async_trait, verificar_assinatura, Ambiente, ArmazenamentoArquivo, ArmazenamentoNsu,
+    ClientBuilder, ClientError, ClientResult, Dfe, DfeError, Documento, Manifestacao, Modelo,
+    Pkcs12Certificate, Processamento, RetConsCad, RetConsSitNFe, RetConsStatServ, RetEnvEvento,
+    RetEnviNFe, SchemaDfe, SincronizacaoError, Sincronizador, SituacaoNFe, Tentativas, Transport,
+    Uf, WebServices, ESPERA_SINCRONIZACAO,
+};
+use fiscalidade_mock::{
+    Cenario, MockSefaz, Operacao, CERTIFICADO_AC, CHAVE, CNPJ, CNPJ_FILIAL, PKCS12_FILIAL,
+};
+
+/// Chave de NFC-e do mesmo emitente da chave do mock.
+const CHAVE_NFCE: &str = "51240111222333000181650010000000011000000014";
+const PROTOCOLO: &str = "151240000000001";
+const JUSTIFICATIVA: &str = "Justificativa de teste do mock";
+
+fn pkcs12() -> Pkcs12Certificate {
+    Pkcs12Certificate::from_bytes(fiscalidade_mock::PKCS12, fiscalidade_mock::SENHA_PKCS12).unwrap()
+}
+
+fn dfe(mock: &MockSefaz) -> Dfe {
+    Dfe::new()
+        .set_webservices(WebServices::from_str(&mock.webservices()).unwrap())
+        .set_pkcs12(pkcs12())
+        .add_root_certificate(CERTIFICADO_AC)
+}
+
+/// Diretório temporário vazio para o armazenamento de NSU de cada teste.
+fn diretorio(nome: &str) -> PathBuf {
+    let diretorio = env::temp_dir().join(format!("fiscalidade-{}-{}", nome, process::id()));
+    let _ = fs::remove_dir_all(&diretorio);
+    diretorio
+}
+
+fn nfe(chave: &str) -> String {
+    format!(
+        concat!(
+            "<NFe xmlns=\"http://www.portalfiscal.inf.br/nfe\">",
+            "<infNFe Id=\"NFe{}\" versao=\"4.00\">",
+            "<ide><cUF>51</cUF><tpAmb>2</tpAmb></ide>",
+            "</infNFe>",
+            "</NFe>"
+        ),
+        chave
+    )
+}
+
+/// NF-e emitida em contingência EPEC, com os dados exigidos no registro do evento.
+fn nfe_epec(chave: &str, c_uf: &str, modelo: &str, tp_emis: &str) -> String {
+    format!(
+        concat!(
+            "<NFe xmlns=\"http://www.portalfiscal.inf.br/nfe\">",
+            "<infNFe Id=\"NFe{chave}\" versao=\"4.00\">",
+            "<ide><cUF>{c_uf}</cUF><mod>{modelo}</mod><dhEmi>2024-01-10T10:00:00-04:00</dhEmi>",
+            "<tpNF>1</tpNF><tpEmis>{tp_emis}</tpEmis><tpAmb>2</tpAmb></ide>",
+            "<emit><CNPJ>{cnpj}</CNPJ><IE>131234565</IE></emit>",
+            "<dest><CPF>52998224725</CPF><enderDest><UF>SP</UF></enderDest></dest>",
+            "<total><ICMSTot><vICMS>18.00</vICMS><vST>0.00</vST><vNF>100.00</vNF></ICMSTot></total>",
+            "</infNFe>",
+            "</NFe>"
+        ),
+        chave = chave,
+        c_uf = c_uf,
+        modelo = modelo,
+        tp_emis = tp_emis,
+        cnpj = CNPJ,
+    )
+}
+
+fn rejeitado(c_stat: u16) -> Cenario {
+    Cenario::Rejeitado {
+        c_stat,
+        x_motivo: "Rejeição do mock".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn status_servico() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let xml = dfe(&mock)
+        .status_servico(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao)
+        .await?;
+    let ret: RetConsStatServ = xml.retorno()?;
+    assert!(ret.em_operacao());
+    assert_eq!(ret.c_uf, "51");
+    let requisicoes = mock.requisicoes();
+    assert_eq!(requisicoes.len(), 1);
+    assert_eq!(requisicoes[0].operacao, Some(Operacao::ConsStatServ));
+    assert!(requisicoes[0].action.ends_with("NFeStatusServico4"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn soap_fault() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario(Cenario::SoapFault);
+    let err = dfe(&mock)
+        .status_servico(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        DfeError::Client(ClientError::SoapFault { status: 500, .. })
+    ));
+    Ok(())
+}
+
+/// Transporte que registra as requisições e responde sempre com o mesmo XML, sem acessar a rede.
+#[derive(Clone, Default)]
+struct Gravador {
+    requisicoes: Arc<Mutex<Vec<Gravacao>>>,
+}
+
+struct Gravacao {
+    url: String,
+    action: String,
+    envelope: String,
+    idempotente: bool,
+}
+
+impl Gravador {
+    const RESPOSTA: &'static str = concat!(
+        "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\"><soap:Body>",
+        "<nfeResultMsg xmlns=\"http://www.portalfiscal.inf.br/nfe/wsdl/NFeStatusServico4\">",
+        "<retConsStatServ xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+        "<tpAmb>2</tpAmb><verAplic>GRAVADOR</verAplic><cStat>107</cStat>",
+        "<xMotivo>Servico em Operacao</xMotivo><cUF>51</cUF>",
+        "<dhRecbto>2024-01-10T10:00:00-04:00</dhRecbto><tMed>3</tMed>",
+        "</retConsStatServ></nfeResultMsg></soap:Body></soap:Envelope>"
+    );
+
+    fn gravar(&self, url: &str, action: &str, xml: Vec<u8>, idempotente: bool) -> ClientResult {
+        self.requisicoes.lock().unwrap().push(Gravacao {
+            url: url.to_string(),
+            action: action.to_string(),
+            envelope: String::from_utf8(xml).unwrap(),
+            idempotente,
+        });
+        Ok(Self::RESPOSTA.as_bytes().to_vec())
+    }
+}
+
+#[async_trait]
+impl Transport for Gravador {
+    async fn execute(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
+        self.gravar(url, action, xml, true)
+    }
+
+    async fn execute_nao_idempotente(&self, url: &str, action: &str, xml: Vec<u8>) -> ClientResult {
+        self.gravar(url, action, xml, false)
+    }
+}
+
+#[tokio::test]
+async fn transporte_personalizado() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let gravador = Gravador::default();
+    let xml = dfe(&mock)
+        .set_transport(gravador.clone())
+        .status_servico(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao)
+        .await?;
+    let ret: RetConsStatServ = xml.retorno()?;
+    assert_eq!(ret.ver_aplic, "GRAVADOR");
+    assert_eq!(ret.t_med, Some(3));
+    // O servidor não é acessado, apenas o transporte informado.
+    assert!(mock.requisicoes().is_empty());
+    let requisicoes = gravador.requisicoes.lock().unwrap();
+    assert_eq!(requisicoes.len(), 1);
+    let gravacao = &requisicoes[0];
+    assert!(gravacao.url.starts_with("https://localhost:"));
+    assert!(gravacao.action.ends_with("NFeStatusServico4"));
+    assert!(gravacao.envelope.contains("<soap12:Envelope"));
+    assert!(gravacao.envelope.contains("<consStatServ"));
+    assert!(gravacao
+        .envelope
+        .contains("<tpAmb>2</tpAmb><cUF>51</cUF><xServ>STATUS</xServ>"));
+    assert!(gravacao.idempotente);
+    Ok(())
+}
+
+/// Política com duas tentativas e espera curta, para não atrasar os testes.
+fn tentativas() -> Tentativas {
+    Tentativas::new(2)
+        .set_espera_inicial(Duration::from_millis(10))
+        .set_espera_maxima(Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn tentativas_idempotente() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario(Cenario::Indisponivel);
+    let xml = dfe(&mock)
+        .set_tentativas(tentativas())
+        .status_servico(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao)
+        .await?;
+    let ret: RetConsStatServ = xml.retorno()?;
+    assert_eq!(ret.c_stat, 107);
+    assert_eq!(mock.requisicoes().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn tentativas_nao_idempotente() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario(Cenario::Indisponivel);
+    let nfe = nfe(CHAVE);
+    let nfes = [nfe.as_str()];
+    let autorizar = |tentativas| {
+        dfe(&mock).set_tentativas(tentativas).autorizar(
+            Modelo::Nfe,
+            Uf::Mt,
+            Ambiente::Homologacao,
+            "1",
+            Processamento::Sincrono,
+            &nfes,
+        )
+    };
+    let err = autorizar(tentativas()).await.unwrap_err();
+    assert!(matches!(
+        err,
+        DfeError::Client(ClientError::Http { status: 503, .. })
+    ));
+    assert_eq!(mock.requisicoes().len(), 1);
+
+    mock.set_cenario(Cenario::Indisponivel);
+    autorizar(tentativas().set_nao_idempotentes(true)).await?;
+    assert_eq!(mock.requisicoes().len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn tentativas_repetivel() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario(Cenario::Indisponivel);
+    let err = dfe(&mock)
+        .set_tentativas(
+            tentativas().set_repetivel(|err| !matches!(err, ClientError::Http { status: 503, .. })),
+        )
+        .status_servico(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        DfeError::Client(ClientError::Http { status: 503, .. })
+    ));
+    assert_eq!(mock.requisicoes().len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn timeout() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario(Cenario::Timeout);
+    let cli = ClientBuilder::new()
+        .set_pkcs12(pkcs12())
+        .add_root_certificate(CERTIFICADO_AC)
+        .set_timeout(Duration::from_millis(500))
+        .build()?;
+    let err = cli
+        .execute(mock.url(), "", b"<consStatServ/>".to_vec())
+        .await
+        .unwrap_err();
+    assert!(err.repetivel());
+    assert!(matches!(err, ClientError::HttpClient(ref err) if err.is_timeout()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn certificado_nao_confiavel() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let err = Dfe::new()
+        .set_webservices(WebServices::from_str(&mock.webservices())?)
+        .set_pkcs12(pkcs12())
+        .status_servico(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::Client(ClientError::HttpClient(_))));
+    assert!(mock.requisicoes().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn consultar_protocolo() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let xml = dfe(&mock)
+        .consultar_protocolo(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao, CHAVE)
+        .await?;
+    let ret: RetConsSitNFe = xml.retorno()?;
+    assert_eq!(ret.situacao(), Some(SituacaoNFe::Autorizada));
+    assert_eq!(ret.ch_nfe, CHAVE);
+
+    mock.set_cenario(rejeitado(217));
+    let xml = dfe(&mock)
+        .consultar_protocolo(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao, CHAVE)
+        .await?;
+    let ret: RetConsSitNFe = xml.retorno()?;
+    assert_eq!(ret.c_stat, 217);
+    assert_eq!(ret.situacao(), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn consultar_cadastro() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let xml = dfe(&mock)
+        .consultar_cadastro(
+            Modelo::Nfe,
+            Uf::Mt,
+            Ambiente::Homologacao,
+            Documento::from_cnpj(CNPJ),
+        )
+        .await?;
+    let ret: RetConsCad = xml.retorno()?;
+    assert_eq!(ret.c_stat, 111);
+    assert_eq!(ret.cnpj.as_deref(), Some(CNPJ));
+    assert_eq!(ret.inf_cad.len(), 1);
+    assert_eq!(ret.inf_cad[0].cnpj.as_deref(), Some(CNPJ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn autorizar_lote() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let nfe = nfe(CHAVE);
+    let prot = dfe(&mock)
+        .autorizar_lote(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao, "1", &[&nfe], 3)
+        .await?;
+    assert_eq!(prot.len(), 1);
+    assert_eq!(prot[0].ch_nfe, CHAVE);
+    assert_eq!(prot[0].c_stat, 100);
+    assert!(prot[0].n_prot.is_some());
+    let operacoes: Vec<_> = mock
+        .requisicoes()
+        .into_iter()
+        .map(|requisicao| requisicao.operacao)
+        .collect();
+    assert_eq!(
+        operacoes,
+        [Some(Operacao::EnviNfe), Some(Operacao::ConsReciNfe)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn autorizar_lote_em_processamento() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario_operacao(Operacao::ConsReciNfe, Cenario::EmProcessamento);
+    let nfe = nfe(CHAVE);
+    let err = dfe(&mock)
+        .autorizar_lote(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao, "1", &[&nfe], 1)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::LoteEmProcessamento(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn autorizar_lote_rejeitado() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario_operacao(Operacao::EnviNfe, rejeitado(225));
+    let nfe = nfe(CHAVE);
+    let err = dfe(&mock)
+        .autorizar_lote(Modelo::Nfe, Uf::Mt, Ambiente::Homologacao, "1", &[&nfe], 1)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::Rejeicao { c_stat: 225, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn autorizar_sincrono() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let nfe = nfe(CHAVE);
+    let xml = dfe(&mock)
+        .autorizar(
+            Modelo::Nfe,
+            Uf::Mt,
+            Ambiente::Homologacao,
+            "1",
+            Processamento::Sincrono,
+            &[&nfe],
+        )
+        .await?;
+    let ret: RetEnviNFe = xml.retorno()?;
+    assert_eq!(ret.c_stat, 104);
+    assert_eq!(ret.prot_nfe.map(|prot| prot.c_stat), Some(100));
+
+    mock.set_cenario(rejeitado(539));
+    let err = dfe(&mock)
+        .autorizar(
+            Modelo::Nfe,
+            Uf::Mt,
+            Ambiente::Homologacao,
+            "1",
+            Processamento::Sincrono,
+            &[&nfe],
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::Rejeicao { c_stat: 539, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn autorizar_assincrono_rejeitado() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario(rejeitado(225));
+    let nfe = nfe(CHAVE);
+    let err = dfe(&mock)
+        .autorizar(
+            Modelo::Nfe,
+            Uf::Mt,
+            Ambiente::Homologacao,
+            "1",
+            Processamento::Assincrono,
+            &[&nfe],
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::Rejeicao { c_stat: 225, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelar() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .cancelar(Ambiente::Homologacao, CHAVE, PROTOCOLO, JUSTIFICATIVA, None)
+        .await?;
+    assert_eq!(ret.c_stat, 128);
+    assert_eq!(ret.ret_evento[0].c_stat, 135);
+    assert_eq!(ret.ret_evento[0].ch_nfe.as_deref(), Some(CHAVE));
+    // Assim como a SEFAZ, verifica a assinatura na mensagem extraída do envelope SOAP, pois a
+    // canonicalização inclui os namespaces declarados no envelope.
+    let corpo = &mock.requisicoes()[0].corpo;
+    let inicio = corpo.find("<envEvento").unwrap();
+    let fim = corpo.find("</envEvento>").unwrap() + "</envEvento>".len();
+    let assinante = verificar_assinatura(&corpo[inicio..fim])?;
+    assert_eq!(assinante.cnpj.as_deref(), Some(CNPJ));
+
+    mock.set_cenario(rejeitado(573));
+    let err = dfe(&mock)
+        .cancelar(Ambiente::Homologacao, CHAVE, PROTOCOLO, JUSTIFICATIVA, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::Rejeicao { c_stat: 573, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelar_outro_certificado() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    // Ao trocar o certificado, os XMLs passam a ser assinados com o novo, e não com o anterior.
+    let filial = Pkcs12Certificate::from_bytes(PKCS12_FILIAL, fiscalidade_mock::SENHA_PKCS12)?;
+    dfe(&mock)
+        .set_pkcs12(filial)
+        .cancelar(Ambiente::Homologacao, CHAVE, PROTOCOLO, JUSTIFICATIVA, None)
+        .await?;
+    let corpo = &mock.requisicoes()[0].corpo;
+    let inicio = corpo.find("<envEvento").unwrap();
+    let fim = corpo.find("</envEvento>").unwrap() + "</envEvento>".len();
+    let assinante = verificar_assinatura(&corpo[inicio..fim])?;
+    assert_eq!(assinante.cnpj.as_deref(), Some(CNPJ_FILIAL));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelar_por_substituicao() -> anyhow::Result<()> {
+    // NFC-e substituta do mesmo emitente, com outro número.
+    let substituta = "51240111222333000181650010000000021000000020";
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .cancelar_por_substituicao(
+            Ambiente::Homologacao,
+            CHAVE_NFCE,
+            PROTOCOLO,
+            substituta,
+            JUSTIFICATIVA,
+        )
+        .await?;
+    assert_eq!(ret.ret_evento[0].c_stat, 135);
+    assert_eq!(ret.ret_evento[0].tp_evento.as_deref(), Some("110112"));
+    let corpo = &mock.requisicoes()[0].corpo;
+    assert!(mock.requisicoes()[0].action.ends_with("NFeRecepcaoEvento4"));
+    assert!(corpo.contains("<cOrgao>51</cOrgao>"));
+    assert!(corpo.contains("<tpEvento>110112</tpEvento>"));
+    assert!(corpo.contains("<cOrgaoAutor>51</cOrgaoAutor>"));
+    assert!(corpo.contains(&format!("<nProt>{}</nProt>", PROTOCOLO)));
+    assert!(corpo.contains(&format!("<chNFeRef>{}</chNFeRef>", substituta)));
+
+    // Disponível apenas para NFC-e, substituída por outra NFC-e da mesma UF.
+    for (chave, substituta) in [
+        (CHAVE, substituta),
+        (CHAVE_NFCE, CHAVE),
+        (CHAVE_NFCE, CHAVE_NFCE),
+    ] {
+        let err = dfe(&mock)
+            .cancelar_por_substituicao(
+                Ambiente::Homologacao,
+                chave,
+                PROTOCOLO,
+                substituta,
+                JUSTIFICATIVA,
+            )
+            .await
+            .unwrap_err();
+        if chave == CHAVE {
+            assert!(matches!(err, DfeError::ModeloNaoSuportado(Modelo::Nfe)));
+        } else {
+            assert!(matches!(err, DfeError::ChaveInvalida(c) if c == substituta));
+        }
+    }
+    assert_eq!(mock.requisicoes().len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn carta_correcao_emitente_cpf() -> anyhow::Result<()> {
+    // Chave de nota emitida por pessoa física, com o CPF precedido de `000` no lugar do CNPJ.
+    let chave = "51240100052998224725550010000000011000000010";
+    let mock = MockSefaz::iniciar().await?;
+    dfe(&mock)
+        .carta_correcao(Ambiente::Homologacao, chave, 1, "Correção de teste do mock")
+        .await?;
+    let corpo = &mock.requisicoes()[0].corpo;
+    assert!(corpo.contains("<CPF>52998224725</CPF>"));
+    assert!(!corpo.contains("<CNPJ>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn carta_correcao_invalida() -> anyhow::Result<()> {
+    async fn carta_correcao(
+        mock: &MockSefaz,
+        chave: &str,
+        sequencia: u8,
+        correcao: &str,
+    ) -> Result<RetEnvEvento, DfeError> {
+        dfe(mock)
+            .carta_correcao(Ambiente::Homologacao, chave, sequencia, correcao)
+            .await
+    }
+
+    let mock = MockSefaz::iniciar().await?;
+    let correcao = "Correção de teste do mock";
+    assert!(matches!(
+        carta_correcao(&mock, CHAVE_NFCE, 1, correcao).await,
+        Err(DfeError::ModeloNaoSuportado(Modelo::Nfce))
+    ));
+    assert!(matches!(
+        carta_correcao(&mock, &CHAVE[..43], 1, correcao).await,
+        Err(DfeError::ChaveInvalida(_))
+    ));
+    for sequencia in [0, 21] {
+        assert!(matches!(
+            carta_correcao(&mock, CHAVE, sequencia, correcao).await,
+            Err(DfeError::SequenciaInvalida(s)) if s == sequencia
+        ));
+    }
+    for correcao in ["Curta demais".to_string(), "a".repeat(1001)] {
+        assert!(matches!(
+            carta_correcao(&mock, CHAVE, 1, &correcao).await,
+            Err(DfeError::CorrecaoInvalida)
+        ));
+    }
+    assert!(mock.requisicoes().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn manifestar() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    for (manifestacao, tipo, justificativa) in [
+        (Manifestacao::Ciencia, "210210", None),
+        (Manifestacao::Confirmacao, "210200", None),
+        (Manifestacao::Desconhecimento, "210220", None),
+        (
+            Manifestacao::OperacaoNaoRealizada,
+            "210240",
+            Some(JUSTIFICATIVA),
+        ),
+    ] {
+        let ret = dfe(&mock)
+            .manifestar(
+                Ambiente::Homologacao,
+                CHAVE,
+                Documento::from_cnpj(CNPJ_FILIAL),
+                manifestacao,
+                justificativa,
+            )
+            .await?;
+        assert_eq!(ret.ret_evento[0].c_stat, 135);
+        assert_eq!(ret.ret_evento[0].tp_evento.as_deref(), Some(tipo));
+        let corpo = mock.requisicoes().pop().unwrap().corpo;
+        assert!(corpo.contains(&format!("<tpEvento>{}</tpEvento>", tipo)));
+        // Eventos de manifestação são registrados no Ambiente Nacional.
+        assert!(corpo.contains("<cOrgao>91</cOrgao>"));
+        assert!(corpo.contains(&format!("<CNPJ>{}</CNPJ>", CNPJ_FILIAL)));
+        assert_eq!(
+            corpo.contains(&format!("<xJust>{}</xJust>", JUSTIFICATIVA)),
+            justificativa.is_some()
+        );
+    }
+    let requisicoes = mock.requisicoes().len();
+
+    // Operação não realizada exige justificativa.
+    for justificativa in [None, Some("Curta demais")] {
+        assert!(matches!(
+            dfe(&mock)
+                .manifestar(
+                    Ambiente::Homologacao,
+                    CHAVE,
+                    Documento::from_cnpj(CNPJ_FILIAL),
+                    Manifestacao::OperacaoNaoRealizada,
+                    justificativa,
+                )
+                .await,
+            Err(DfeError::JustificativaInvalida)
+        ));
+    }
+    assert!(matches!(
+        dfe(&mock)
+            .manifestar(
+                Ambiente::Homologacao,
+                CHAVE,
+                Documento::from_ie("123456789"),
+                Manifestacao::Ciencia,
+                None,
+            )
+            .await,
+        Err(DfeError::DocumentoInvalido(_))
+    ));
+    assert!(matches!(
+        dfe(&mock)
+            .manifestar(
+                Ambiente::Homologacao,
+                CHAVE_NFCE,
+                Documento::from_cnpj(CNPJ_FILIAL),
+                Manifestacao::Ciencia,
+                None,
+            )
+            .await,
+        Err(DfeError::ModeloNaoSuportado(Modelo::Nfce))
+    ));
+    assert_eq!(mock.requisicoes().len(), requisicoes);
+    Ok(())
+}
+
+#[tokio::test]
+async fn enviar_epec() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .enviar_epec(Ambiente::Homologacao, &nfe_epec(CHAVE, "51", "55", "4"))
+        .await?;
+    // EPEC é registrado antes da autorização, portanto não vinculado à nota.
+    assert_eq!(ret.ret_evento[0].c_stat, 136);
+    assert_eq!(ret.ret_evento[0].tp_evento.as_deref(), Some("110140"));
+    let corpo = &mock.requisicoes()[0].corpo;
+    assert!(corpo.contains("<cOrgao>91</cOrgao>"));
+    assert!(corpo.contains("<cOrgaoAutor>51</cOrgaoAutor>"));
+    assert!(corpo.contains("<dest><UF>SP</UF><CPF>52998224725</CPF><vNF>100.00</vNF>"));
+
+    mock.set_cenario(rejeitado(467));
+    let err = dfe(&mock)
+        .enviar_epec(Ambiente::Homologacao, &nfe_epec(CHAVE, "51", "55", "4"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::Rejeicao { c_stat: 467, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn enviar_epec_invalido() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let enviar = |nfe: String| {
+        let dfe = dfe(&mock);
+        async move { dfe.enviar_epec(Ambiente::Homologacao, &nfe).await }
+    };
+    assert!(matches!(
+        enviar(nfe_epec(CHAVE_NFCE, "51", "65", "4")).await,
+        Err(DfeError::ModeloNaoSuportado(Modelo::Nfce))
+    ));
+    assert!(matches!(
+        enviar(nfe_epec(CHAVE, "51", "55", "1")).await,
+        Err(DfeError::TipoEmissaoInvalido(tp_emis)) if tp_emis == "1"
+    ));
+    // Modelo e UF da identificação divergentes dos contidos na chave.
+    assert!(matches!(
+        enviar(nfe_epec(CHAVE_NFCE, "51", "55", "4")).await,
+        Err(DfeError::ChaveInvalida(chave)) if chave == CHAVE_NFCE
+    ));
+    assert!(matches!(
+        enviar(nfe_epec(CHAVE, "35", "55", "4")).await,
+        Err(DfeError::ChaveInvalida(chave)) if chave == CHAVE
+    ));
+    assert!(mock.requisicoes().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn inutilizar() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .inutilizar(
+            Modelo::Nfe,
+            Uf::Mt,
+            Ambiente::Homologacao,
+            2024,
+            CNPJ,
+            1,
+            10,
+            20,
+            JUSTIFICATIVA,
+        )
+        .await?;
+    assert_eq!(ret.c_stat, 102);
+    assert_eq!(ret.cnpj.as_deref(), Some(CNPJ));
+    assert!(ret.n_prot.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn distribuicao() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .distribuicao_por_ultimo_nsu(Uf::Mt, Ambiente::Homologacao, Documento::from_cnpj(CNPJ), 0)
+        .await?;
+    assert_eq!(ret.c_stat, 138);
+    assert_eq!(ret.documentos.len(), 1);
+    assert_eq!(ret.documentos[0].nsu, 1);
+    assert_eq!(ret.documentos[0].schema, SchemaDfe::ResNFe);
+    assert!(ret.documentos[0].xml.contains(CHAVE));
+
+    let ret = dfe(&mock)
+        .distribuicao_por_ultimo_nsu(
+            Uf::Mt,
+            Ambiente::Homologacao,
+            Documento::from_cnpj(CNPJ),
+            ret.ult_nsu,
+        )
+        .await?;
+    assert_eq!(ret.c_stat, 137);
+    assert!(ret.documentos.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn distribuicao_por_chave() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .distribuicao_por_chave(
+            Uf::Mt,
+            Ambiente::Homologacao,
+            Documento::from_cnpj(CNPJ),
+            CHAVE_NFCE,
+        )
+        .await?;
+    assert_eq!(ret.c_stat, 138);
+    assert_eq!(ret.documentos.len(), 1);
+    assert!(ret.documentos[0].xml.contains(CHAVE_NFCE));
+    let corpo = &mock.requisicoes()[0].corpo;
+    assert!(corpo.contains(&format!(
+        "<consChNFe><chNFe>{}</chNFe></consChNFe>",
+        CHAVE_NFCE
+    )));
+    assert!(corpo.contains("<cUFAutor>51</cUFAutor>"));
+
+    let err = dfe(&mock)
+        .distribuicao_por_chave(
+            Uf::Mt,
+            Ambiente::Homologacao,
+            Documento::from_cnpj(CNPJ),
+            &CHAVE[1..],
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DfeError::ChaveInvalida(_)));
+    assert_eq!(mock.requisicoes().len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn distribuicao_por_nsu() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let ret = dfe(&mock)
+        .distribuicao_por_nsu(Uf::Mt, Ambiente::Homologacao, Documento::from_cnpj(CNPJ), 1)
+        .await?;
+    assert_eq!(ret.c_stat, 138);
+    assert_eq!(ret.documentos.len(), 1);
+    assert_eq!(ret.documentos[0].nsu, 1);
+    assert!(mock.requisicoes()[0]
+        .corpo
+        .contains("<consNSU><NSU>000000000000001</NSU></consNSU>"));
+
+    // NSU ainda não disponível.
+    let ret = dfe(&mock)
+        .distribuicao_por_nsu(Uf::Mt, Ambiente::Homologacao, Documento::from_cnpj(CNPJ), 2)
+        .await?;
+    assert_eq!(ret.c_stat, 137);
+    assert!(ret.documentos.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn sincronizar() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let armazenamento = ArmazenamentoArquivo::new(diretorio("sincronizar")).await?;
+    let sincronizador =
+        Sincronizador::new(dfe(&mock), armazenamento, Uf::Mt, Ambiente::Homologacao);
+    let inicio = Utc::now().timestamp();
+    let mut documentos = Vec::new();
+    let estado = sincronizador
+        .sincronizar(Documento::from_cnpj(CNPJ), |documento| {
+            documentos.push(documento);
+            Ok(())
+        })
+        .await?;
+    assert_eq!(documentos.len(), 1);
+    assert_eq!(estado.ult_nsu, 1);
+    assert_eq!(estado.max_nsu, 1);
+    assert!(estado.proxima_consulta >= inicio + ESPERA_SINCRONIZACAO);
+    assert!(estado.proxima_consulta <= Utc::now().timestamp() + ESPERA_SINCRONIZACAO);
+
+    // Nova sincronização antes de uma hora é recusada sem consultar a SEFAZ.
+    let requisicoes = mock.requisicoes().len();
+    let err = sincronizador
+        .sincronizar(Documento::from_cnpj(CNPJ), |_| Ok(()))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        SincronizacaoError::ConsultaBloqueada { ate, .. } if ate == estado.proxima_consulta
+    ));
+    assert_eq!(mock.requisicoes().len(), requisicoes);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sincronizar_erro_processamento() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    let armazenamento = ArmazenamentoArquivo::new(diretorio("sincronizar-processamento")).await?;
+    let sincronizador = Sincronizador::new(
+        dfe(&mock),
+        armazenamento.clone(),
+        Uf::Mt,
+        Ambiente::Homologacao,
+    );
+    let err = sincronizador
+        .sincronizar(Documento::from_cnpj(CNPJ), |_| {
+            Err("falha ao gravar documento".into())
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        &err,
+        SincronizacaoError::Processamento { nsu: 1, erro } if erro.to_string() == "falha ao gravar documento"
+    ));
+    // O NSU não é armazenado, então o documento é recebido novamente na próxima sincronização.
+    assert_eq!(armazenamento.carregar(CNPJ).await?, None);
+    let mut documentos = Vec::new();
+    let estado = sincronizador
+        .sincronizar(Documento::from_cnpj(CNPJ), |documento| {
+            documentos.push(documento.nsu);
+            Ok(())
+        })
+        .await?;
+    assert_eq!(documentos, [1]);
+    assert_eq!(estado.ult_nsu, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn armazenamento_documento_invalido() -> anyhow::Result<()> {
+    let diretorio = diretorio("armazenamento-invalido");
+    let armazenamento = ArmazenamentoArquivo::new(diretorio.join("nsu")).await?;
+    for documento in ["", "../00000000000191", "/tmp/x", "0000000000019a"] {
+        assert!(matches!(
+            armazenamento.bloquear(documento).await,
+            Err(SincronizacaoError::DocumentoInvalido(d)) if d == documento
+        ));
+        assert!(matches!(
+            armazenamento.salvar(documento, &Default::default()).await,
+            Err(SincronizacaoError::DocumentoInvalido(_))
+        ));
+        assert!(matches!(
+            armazenamento.carregar(documento).await,
+            Err(SincronizacaoError::DocumentoInvalido(_))
+        ));
+    }
+    assert!(!diretorio.join("00000000000191.lock").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn sincronizar_consumo_indevido() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    mock.set_cenario_operacao(Operacao::DistDfeInt, rejeitado(656));
+    let armazenamento = ArmazenamentoArquivo::new(diretorio("consumo-indevido")).await?;
+    let sincronizador = Sincronizador::new(
+        dfe(&mock),
+        armazenamento.clone(),
+        Uf::Mt,
+        Ambiente::Homologacao,
+    );
+    let inicio = Utc::now().timestamp();
+    let err = sincronizador
+        .sincronizar(Documento::from_cnpj(CNPJ), |_| Ok(()))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SincronizacaoError::ConsumoIndevido(_)));
+    let estado = armazenamento.carregar(CNPJ).await?.unwrap();
+    assert_eq!(estado.ult_nsu, 0);
+    assert!(estado.proxima_consulta >= inicio + ESPERA_SINCRONIZACAO);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sincronizar_em_andamento() -> anyhow::Result<()> {
+    let mock = MockSefaz::iniciar().await?;
+    // A primeira sincronização fica aguardando a SEFAZ enquanto a segunda tenta iniciar.
+    mock.set_cenario(Cenario::Timeout);
+    let cli = ClientBuilder::new()
+        .set_pkcs12(pkcs12())
+        .add_root_certificate(CERTIFICADO_AC)
+        .set_timeout(Duration::from_millis(500))
+        .build()?;
+    let armazenamento = ArmazenamentoArquivo::new(diretorio("em-andamento")).await?;
+    let sincronizador = Sincronizador::new(
+        dfe(&mock).set_transport(cli),
+        armazenamento,
+        Uf::Mt,
+        Ambiente::Homologacao,
+    );
+    let (primeira, segunda) = tokio::join!(
+        sincronizador.sincronizar(Documento::from_cnpj(CNPJ), |_| Ok(())),
+        async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sincronizador
+                .sincronizar(Documento::from_cnpj(CNPJ), |_| Ok(()))
+                .await
+        }
+    );
+    assert!(matches!(
+        primeira,
+        Err(SincronizacaoError::Dfe(DfeError::Client(_)))
+    ));
+    assert!(
+        matches!(segunda, Err(SincronizacaoError::EmAndamento(ref documento)) if documento == CNPJ)
+    );
+    assert_eq!(mock.requisicoes().len(), 1);
+    Ok(())
+}
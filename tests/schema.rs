@@ -0,0 +1,91 @@
+#![cfg(feature = "schemas")]
+
+use std::{env, fs, process};
+
+use fiscalidade::{Schema, SchemaError, Schemas};
+
+const DIRETORIO: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/schemas");
+
+const VALIDO: &str = concat!(
+    "<consStatServ xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">",
+    "<tpAmb>2</tpAmb><cUF>51</cUF><xServ>STATUS</xServ>",
+    "</consStatServ>"
+);
+
+const INVALIDO: &str = concat!(
+    "<consStatServ xmlns=\"http://www.portalfiscal.inf.br/nfe\" versao=\"4.00\">\n",
+    "<tpAmb>3</tpAmb>\n<cUF>51</cUF>\n",
+    "</consStatServ>"
+);
+
+#[test]
+fn validar() -> anyhow::Result<()> {
+    let schemas = Schemas::from_dir(DIRETORIO);
+    assert!(schemas.validar(VALIDO, Schema::ConsStatServ400)?.is_empty());
+    let erros = schemas.validar(INVALIDO, Schema::ConsStatServ400)?;
+    assert_eq!(erros.len(), 2);
+    assert_eq!(erros[0].linha, 2);
+    assert!(erros[0].mensagem.contains("tpAmb"));
+    // Elementos ausentes são apontados na linha do elemento pai.
+    assert_eq!(erros[1].linha, 1);
+    assert!(erros[1].mensagem.contains("xServ"));
+    Ok(())
+}
+
+#[test]
+fn schema_nao_encontrado() {
+    assert!(matches!(
+        Schemas::from_dir(DIRETORIO).validar(VALIDO, Schema::Nfe400),
+        Err(SchemaError::SchemaNaoEncontrado(_))
+    ));
+}
+
+#[test]
+fn xml_invalido() {
+    assert!(matches!(
+        Schemas::from_dir(DIRETORIO).validar("<consStatServ>", Schema::ConsStatServ400),
+        Err(SchemaError::XmlInvalido(_))
+    ));
+}
+
+#[test]
+fn schema_compilado_uma_vez() -> anyhow::Result<()> {
+    let diretorio = env::temp_dir().join(format!("fiscalidade-schemas-{}", process::id()));
+    fs::create_dir_all(&diretorio)?;
+    for arquivo in fs::read_dir(DIRETORIO)? {
+        let arquivo = arquivo?;
+        fs::copy(arquivo.path(), diretorio.join(arquivo.file_name()))?;
+    }
+    let schemas = Schemas::from_dir(&diretorio);
+    assert!(schemas.validar(VALIDO, Schema::ConsStatServ400)?.is_empty());
+    // Após compilado, o schema não é mais lido do diretório, inclusive pelos clones do validador.
+    fs::remove_dir_all(&diretorio)?;
+    assert!(schemas.validar(VALIDO, Schema::ConsStatServ400)?.is_empty());
+    assert_eq!(
+        schemas
+            .clone()
+            .validar(INVALIDO, Schema::ConsStatServ400)?
+            .len(),
+        2
+    );
+    Ok(())
+}
+
+/// NF-e completa assinada, validada contra os schemas do PL_009 embutidos de `resources/schemas`.
+#[cfg(feature = "embed_schemas")]
+#[test]
+fn nfe_embutido() -> anyhow::Result<()> {
+    use fiscalidade::{validar_schema, Assinador, Pkcs12Certificate};
+
+    let pkcs12 =
+        Pkcs12Certificate::from_bytes(fiscalidade_mock::PKCS12, fiscalidade_mock::SENHA_PKCS12)?;
+    let nfe = pkcs12.assinar(
+        include_str!("fixtures/nfe.xml"),
+        "NFe51240111222333000181550010000000011000000010",
+    )?;
+    assert_eq!(validar_schema(&nfe, Schema::Nfe400)?, []);
+    let erros = validar_schema(&nfe.replace("<vNF>10.00</vNF>", ""), Schema::Nfe400)?;
+    assert_eq!(erros.len(), 1);
+    assert!(erros[0].mensagem.contains("vNF"));
+    Ok(())
+}
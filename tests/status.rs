@@ -0,0 +1,56 @@
+use fiscalidade::{ClassificacaoStatus, CodigoStatus};
+
+#[test]
+fn codigos_ordenados() {
+    let codigos: Vec<u16> = CodigoStatus::todos().map(|status| status.codigo).collect();
+    assert!(
+        codigos.windows(2).all(|par| par[0] < par[1]),
+        "catálogo deve estar em ordem estritamente crescente para a busca binária"
+    );
+    for codigo in codigos {
+        assert_eq!(
+            CodigoStatus::from_codigo(codigo).map(|status| status.codigo),
+            Some(codigo)
+        );
+    }
+}
+
+#[test]
+fn classificar_fora_do_catalogo() {
+    // Códigos desconhecidos nunca são tratados como sucesso, nem na faixa 1xx.
+    for codigo in [0, 199, 899, 1000] {
+        assert_eq!(
+            CodigoStatus::classificar(codigo),
+            ClassificacaoStatus::Rejeicao
+        );
+    }
+}
+
+#[test]
+fn classificar_catalogo() {
+    for (codigo, classificacao) in [
+        (100, ClassificacaoStatus::Sucesso),
+        (110, ClassificacaoStatus::Denegacao),
+        (205, ClassificacaoStatus::Rejeicao),
+        (218, ClassificacaoStatus::Rejeicao),
+        (301, ClassificacaoStatus::Denegacao),
+        (656, ClassificacaoStatus::Transitoria),
+        (999, ClassificacaoStatus::Rejeicao),
+    ] {
+        assert_eq!(CodigoStatus::classificar(codigo), classificacao);
+    }
+}
+
+#[test]
+fn rejeicoes_prefixadas() {
+    for status in CodigoStatus::todos()
+        .filter(|status| status.classificacao == ClassificacaoStatus::Rejeicao)
+        .filter(|status| status.codigo >= 200)
+    {
+        assert!(
+            status.descricao.starts_with("Rejeição:"),
+            "{} deve ter o prefixo \"Rejeição:\"",
+            status
+        );
+    }
+}